@@ -70,6 +70,7 @@ fn process_static_files() -> Result<(), Box<dyn std::error::Error>> {
     fs::create_dir_all(format!("{}/css", output_dir))?;
     fs::create_dir_all(format!("{}/js", output_dir))?;
     fs::create_dir_all(format!("{}/js/modules", output_dir))?;
+    fs::create_dir_all(format!("{}/img", output_dir))?;
 
     // Process CSS files
     process_directory(
@@ -81,6 +82,20 @@ fn process_static_files() -> Result<(), Box<dyn std::error::Error>> {
     // Process JS files
     process_js_files(static_dir, output_dir)?;
 
+    // Images are copied as they are
+    for entry in fs::read_dir(format!("{}/img", static_dir))? {
+        let path = entry?.path();
+        if path.is_file() {
+            let output_path = format!(
+                "{}/img/{}",
+                output_dir,
+                path.file_name().unwrap().to_str().unwrap()
+            );
+            fs::copy(&path, &output_path)?;
+            println!("Copied image: {}", output_path);
+        }
+    }
+
     Ok(())
 }
 
@@ -235,7 +250,7 @@ fn minify_js_module(content: &str) -> String {
             '/' if last_char == '/' => {
                 // Line comment - remove last char and skip until newline
                 result.pop();
-                while let Some(next) = chars.next() {
+                for next in chars.by_ref() {
                     if next == '\n' {
                         break;
                     }
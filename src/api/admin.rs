@@ -0,0 +1,368 @@
+use crate::api::tmdb;
+use crate::security::auth::Role;
+use crate::security::users::UserUpdate;
+use crate::{AppState, AuthMode, DiscoveryConfig, DiscoveryOverrides, Error, Result};
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
+
+#[derive(Deserialize)]
+pub struct NewUser {
+    username: String,
+    password: String,
+    #[serde(default)]
+    role: Role,
+}
+
+#[derive(Deserialize)]
+pub struct FeedUpdate {
+    name: Option<String>,
+    #[serde(default)]
+    discovery: DiscoveryOverrides,
+}
+
+/// User management only applies to native accounts.
+fn require_local(state: &AppState) -> Result<()> {
+    if state.config.auth.mode != AuthMode::Local {
+        return Err(Error::BadRequest(
+            "User management needs auth mode \"local\"".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn respond(result: Result<serde_json::Value>) -> Response {
+    match result {
+        Ok(body) => Json(body).into_response(),
+        Err(e) => {
+            error!("Admin request failed: {}", e);
+            let status = match e {
+                Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+                Error::Forbidden(_) => StatusCode::FORBIDDEN,
+                Error::NotFound(_) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (
+                status,
+                Json(json!({
+                    "success": false,
+                    "error": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn list_users(State(state): State<AppState>) -> Response {
+    respond(
+        async {
+            require_local(&state)?;
+            Ok(json!({
+                "success": true,
+                "users": state.users.list().await,
+            }))
+        }
+        .await,
+    )
+}
+
+pub async fn create_user(State(state): State<AppState>, Json(user): Json<NewUser>) -> Response {
+    respond(
+        async {
+            require_local(&state)?;
+            let user = state
+                .users
+                .create(&user.username, &user.password, user.role)
+                .await?;
+            Ok(json!({ "success": true, "user": user }))
+        }
+        .await,
+    )
+}
+
+pub async fn update_user(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+    Json(update): Json<UserUpdate>,
+) -> Response {
+    respond(
+        async {
+            require_local(&state)?;
+            let user = state.users.update(&username, update).await?;
+            Ok(json!({ "success": true, "user": user }))
+        }
+        .await,
+    )
+}
+
+pub async fn delete_user(State(state): State<AppState>, Path(username): Path<String>) -> Response {
+    respond(
+        async {
+            require_local(&state)?;
+            state.users.delete(&username).await?;
+            Ok(json!({ "success": true }))
+        }
+        .await,
+    )
+}
+
+pub async fn revoke_sessions(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+) -> Response {
+    respond(
+        async {
+            require_local(&state)?;
+            state.users.revoke_sessions(&username).await?;
+            Ok(json!({ "success": true }))
+        }
+        .await,
+    )
+}
+
+/// Without sign-in there are no admins, so settings stay as configured.
+fn require_auth(state: &AppState) -> Result<()> {
+    if state.config.auth.mode == AuthMode::None {
+        return Err(Error::BadRequest("Changing settings needs sign-in".into()));
+    }
+    Ok(())
+}
+
+/// Resolves provider names and checks a feed's profile before it is applied.
+async fn prepare_discovery(
+    state: &AppState,
+    feed_id: &str,
+    mut discovery: DiscoveryConfig,
+) -> Result<DiscoveryConfig> {
+    let invalid = |e: String| Error::BadRequest(format!("Feed \"{}\": {}", feed_id, e));
+    tmdb::resolve_discovery_providers(&state.config, &mut discovery)
+        .await
+        .map_err(|e| match e {
+            Error::Config(e) => invalid(e),
+            e => e,
+        })?;
+    discovery.validate().map_err(invalid)?;
+    Ok(discovery)
+}
+
+async fn settings_body(state: &AppState) -> serde_json::Value {
+    let mut feeds = Vec::with_capacity(state.config.feeds.len());
+    for feed in &state.config.feeds {
+        if let Some(feed_state) = state.feed(&feed.id) {
+            feeds.push(feed_state.config.read().await.clone());
+        }
+    }
+
+    json!({
+        "success": true,
+        "discovery": *state.discovery.read().await,
+        "feeds": feeds,
+    })
+}
+
+pub async fn get_settings(State(state): State<AppState>) -> Response {
+    respond(Ok(settings_body(&state).await))
+}
+
+/// Changes the `[discovery]` defaults and rebuilds every feed from them,
+/// keeping what each feed overrides. Lasts until the app restarts.
+pub async fn update_discovery(
+    State(state): State<AppState>,
+    Json(update): Json<DiscoveryOverrides>,
+) -> Response {
+    respond(
+        async {
+            require_auth(&state)?;
+            let mut defaults = state.discovery.write().await;
+            let discovery = update.apply(&defaults);
+
+            // Check every feed before changing any of them
+            let mut rebuilt = Vec::with_capacity(state.config.feeds.len());
+            for feed in &state.config.feeds {
+                let Some(feed_state) = state.feed(&feed.id) else {
+                    continue;
+                };
+                let overrides = feed_state.config.read().await.overrides.clone();
+                let profile =
+                    prepare_discovery(&state, &feed.id, overrides.apply(&discovery)).await?;
+                rebuilt.push((feed_state, profile));
+            }
+
+            *defaults = discovery;
+            for (feed_state, profile) in rebuilt {
+                feed_state.config.write().await.discovery = profile;
+            }
+            drop(defaults);
+
+            info!("Updated the discovery defaults");
+            Ok(settings_body(&state).await)
+        }
+        .await,
+    )
+}
+
+/// Renames a feed or changes what its discovery profile overrides. Lasts until
+/// the app restarts.
+pub async fn update_feed(
+    State(state): State<AppState>,
+    Path(feed_id): Path<String>,
+    Json(update): Json<FeedUpdate>,
+) -> Response {
+    respond(
+        async {
+            require_auth(&state)?;
+            let feed_state = state
+                .feed(&feed_id)
+                .ok_or_else(|| Error::NotFound(format!("Unknown feed: {}", feed_id)))?;
+            if update
+                .name
+                .as_deref()
+                .is_some_and(|name| name.trim().is_empty())
+            {
+                return Err(Error::BadRequest("Feed name must not be empty".into()));
+            }
+
+            let defaults = state.discovery.read().await;
+            let mut feed = feed_state.config.write().await;
+            let overrides = feed.overrides.merge(&update.discovery);
+            feed.discovery =
+                prepare_discovery(&state, &feed_id, overrides.apply(&defaults)).await?;
+            feed.overrides = overrides;
+            if let Some(name) = update.name {
+                feed.name = name.trim().to_string();
+            }
+            drop(feed);
+            drop(defaults);
+
+            info!("Updated feed {}", feed_id);
+            Ok(settings_body(&state).await)
+        }
+        .await,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::security::auth::{CurrentUser, Role};
+    use crate::security::csrf::{CSRF_COOKIE, CSRF_HEADER};
+    use crate::{init_router, testing, AppState, AuthMode};
+    use axum::body::Body;
+    use http::{header::COOKIE, Request, StatusCode};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    async fn signed_in(role: Role) -> (AppState, String) {
+        let state = testing::state(AuthMode::Local);
+        state.users.create("alice", "hunter2", role).await.unwrap();
+        let user = CurrentUser {
+            username: "alice".into(),
+            email: None,
+            jellyseerr_id: None,
+            jellyseerr_permissions: None,
+            role,
+        };
+        let session = state.sessions.issue(&user, 0);
+        let session = session.split(';').next().unwrap();
+        let cookie = format!("{}; {}=browser", session, CSRF_COOKIE);
+        (state, cookie)
+    }
+
+    async fn patch(
+        state: &AppState,
+        cookie: &str,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> StatusCode {
+        init_router(state.clone())
+            .oneshot(
+                Request::patch(uri)
+                    .header(COOKIE, cookie)
+                    .header(CSRF_HEADER, state.csrf.issue("browser"))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn only_admins_change_settings() {
+        let (state, cookie) = signed_in(Role::Requester).await;
+        let status = patch(
+            &state,
+            &cookie,
+            "/api/admin/settings/feeds/latest",
+            json!({ "name": "Renamed" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            state.feed("latest").unwrap().config.read().await.name,
+            "Latest"
+        );
+    }
+
+    #[tokio::test]
+    async fn feeds_keep_their_overrides_when_defaults_change() {
+        let (state, cookie) = signed_in(Role::Admin).await;
+
+        let status = patch(
+            &state,
+            &cookie,
+            "/api/admin/settings/feeds/latest",
+            json!({ "name": "Renamed", "discovery": { "max_pages": 2 } }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let status = patch(
+            &state,
+            &cookie,
+            "/api/admin/settings/discovery",
+            json!({ "region": "CA", "max_pages": 9 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let feed = state.feed("latest").unwrap().config.read().await.clone();
+        assert_eq!(feed.name, "Renamed");
+        assert_eq!(feed.discovery.region, "CA");
+        assert_eq!(feed.discovery.max_pages, 2);
+        assert_eq!(state.discovery.read().await.max_pages, 9);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_settings_without_applying_them() {
+        let (state, cookie) = signed_in(Role::Admin).await;
+
+        let status = patch(
+            &state,
+            &cookie,
+            "/api/admin/settings/discovery",
+            json!({ "max_pages": 0 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let status = patch(
+            &state,
+            &cookie,
+            "/api/admin/settings/feeds/missing",
+            json!({ "name": "Missing" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        assert_eq!(state.discovery.read().await.max_pages, 5);
+        let feed = state.feed("latest").unwrap().config.read().await.clone();
+        assert_eq!(feed.discovery.max_pages, 5);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::api::omdb::OMDBResponse;
-use crate::api::tmdb::TVShowDetails;
+use crate::api::tmdb::{
+    ExternalIds, Genre, RegionWatchProviders, TVShowDetails, WatchProviderEntry,
+};
 use crate::utils::serde::timestamp;
 use crate::Result;
 use dashmap::DashMap;
@@ -27,17 +29,33 @@ pub struct CachedItem<T> {
 struct CacheFile {
     tv_details: Vec<(i32, CachedItem<TVShowDetails>)>,
     omdb_ratings: Vec<(String, CachedItem<OMDBResponse>)>,
+    #[serde(default)]
+    watch_providers: Vec<(String, CachedItem<RegionWatchProviders>)>,
+    #[serde(default)]
+    provider_lists: Vec<(String, CachedItem<Vec<WatchProviderEntry>>)>,
+    #[serde(default)]
+    genres: Vec<(String, CachedItem<Vec<Genre>>)>,
+    #[serde(default)]
+    external_ids: Vec<(String, CachedItem<ExternalIds>)>,
 }
 
 pub enum CacheType {
     TVDetails,
     OMDBRatings,
+    WatchProviders,
+    ProviderLists,
+    Genres,
+    ExternalIds,
 }
 
 #[derive(Clone)]
 pub struct CacheManager {
     tv_details: Arc<DashMap<i32, CachedItem<TVShowDetails>>>,
     omdb_ratings: Arc<DashMap<String, CachedItem<OMDBResponse>>>,
+    watch_providers: Arc<DashMap<String, CachedItem<RegionWatchProviders>>>,
+    provider_lists: Arc<DashMap<String, CachedItem<Vec<WatchProviderEntry>>>>,
+    genres: Arc<DashMap<String, CachedItem<Vec<Genre>>>>,
+    external_ids: Arc<DashMap<String, CachedItem<ExternalIds>>>,
     last_cleanup: Arc<AtomicU64>,
 }
 
@@ -48,6 +66,10 @@ impl CacheManager {
         Self {
             tv_details: Arc::new(DashMap::with_capacity(MAX_CACHE_SIZE)),
             omdb_ratings: Arc::new(DashMap::with_capacity(MAX_CACHE_SIZE)),
+            watch_providers: Arc::new(DashMap::with_capacity(MAX_CACHE_SIZE)),
+            provider_lists: Arc::new(DashMap::new()),
+            genres: Arc::new(DashMap::new()),
+            external_ids: Arc::new(DashMap::with_capacity(MAX_CACHE_SIZE)),
             last_cleanup: Arc::new(AtomicU64::new(0)),
         }
     }
@@ -60,13 +82,13 @@ impl CacheManager {
 
             // Remove expired item without holding the reference
             drop(item);
-            let id = id.clone();
             let tv_details = self.tv_details.clone();
             tokio::spawn(async move {
-                if let Err(_) = timeout(Duration::from_secs(1), async {
+                if timeout(Duration::from_secs(1), async {
                     tv_details.remove(&id);
                 })
                 .await
+                .is_err()
                 {
                     debug!("Timeout while trying to remove expired TV details cache entry");
                 }
@@ -86,10 +108,11 @@ impl CacheManager {
             let key = key.to_string();
             let omdb_ratings = self.omdb_ratings.clone();
             tokio::spawn(async move {
-                if let Err(_) = timeout(Duration::from_secs(1), async {
+                if timeout(Duration::from_secs(1), async {
                     omdb_ratings.remove(&key);
                 })
                 .await
+                .is_err()
                 {
                     debug!("Timeout while trying to remove expired OMDB cache entry");
                 }
@@ -98,6 +121,102 @@ impl CacheManager {
         None
     }
 
+    fn get_watch_providers(&self, key: &str) -> Option<CachedItem<RegionWatchProviders>> {
+        if let Some(item) = self.watch_providers.get(key) {
+            if item.timestamp.elapsed() < CACHE_TTL {
+                return Some(item.clone());
+            }
+
+            // Remove expired item without holding the reference
+            drop(item);
+            let key = key.to_string();
+            let watch_providers = self.watch_providers.clone();
+            tokio::spawn(async move {
+                if timeout(Duration::from_secs(1), async {
+                    watch_providers.remove(&key);
+                })
+                .await
+                .is_err()
+                {
+                    debug!("Timeout while trying to remove expired watch providers cache entry");
+                }
+            });
+        }
+        None
+    }
+
+    fn get_provider_list(&self, key: &str) -> Option<CachedItem<Vec<WatchProviderEntry>>> {
+        if let Some(item) = self.provider_lists.get(key) {
+            if item.timestamp.elapsed() < CACHE_TTL {
+                return Some(item.clone());
+            }
+
+            // Remove expired item without holding the reference
+            drop(item);
+            let key = key.to_string();
+            let provider_lists = self.provider_lists.clone();
+            tokio::spawn(async move {
+                if timeout(Duration::from_secs(1), async {
+                    provider_lists.remove(&key);
+                })
+                .await
+                .is_err()
+                {
+                    debug!("Timeout while trying to remove expired provider list cache entry");
+                }
+            });
+        }
+        None
+    }
+
+    fn get_genres(&self, key: &str) -> Option<CachedItem<Vec<Genre>>> {
+        if let Some(item) = self.genres.get(key) {
+            if item.timestamp.elapsed() < CACHE_TTL {
+                return Some(item.clone());
+            }
+
+            // Remove expired item without holding the reference
+            drop(item);
+            let key = key.to_string();
+            let genres = self.genres.clone();
+            tokio::spawn(async move {
+                if timeout(Duration::from_secs(1), async {
+                    genres.remove(&key);
+                })
+                .await
+                .is_err()
+                {
+                    debug!("Timeout while trying to remove expired genre cache entry");
+                }
+            });
+        }
+        None
+    }
+
+    fn get_external_ids(&self, key: &str) -> Option<CachedItem<ExternalIds>> {
+        if let Some(item) = self.external_ids.get(key) {
+            if item.timestamp.elapsed() < CACHE_TTL {
+                return Some(item.clone());
+            }
+
+            // Remove expired item without holding the reference
+            drop(item);
+            let key = key.to_string();
+            let external_ids = self.external_ids.clone();
+            tokio::spawn(async move {
+                if timeout(Duration::from_secs(1), async {
+                    external_ids.remove(&key);
+                })
+                .await
+                .is_err()
+                {
+                    debug!("Timeout while trying to remove expired external IDs cache entry");
+                }
+            });
+        }
+        None
+    }
+
     fn maybe_cleanup(&self) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -105,15 +224,18 @@ impl CacheManager {
             .as_secs();
 
         let last = self.last_cleanup.load(Ordering::Relaxed);
-        if now - last > CLEANUP_INTERVAL.as_secs() {
-            if self
+        if now - last > CLEANUP_INTERVAL.as_secs()
+            && self
                 .last_cleanup
                 .compare_exchange(last, now, Ordering::SeqCst, Ordering::Relaxed)
                 .is_ok()
-            {
-                Self::cleanup_map(&self.tv_details, "TV Details");
-                Self::cleanup_map(&self.omdb_ratings, "OMDB Ratings");
-            }
+        {
+            Self::cleanup_map(&self.tv_details, "TV Details");
+            Self::cleanup_map(&self.omdb_ratings, "OMDB Ratings");
+            Self::cleanup_map(&self.watch_providers, "Watch Providers");
+            Self::cleanup_map(&self.external_ids, "External IDs");
+            Self::cleanup_map(&self.provider_lists, "Provider Lists");
+            Self::cleanup_map(&self.genres, "Genres");
         }
     }
 
@@ -176,6 +298,50 @@ impl CacheManager {
         self.maybe_cleanup();
     }
 
+    fn insert_watch_providers(&self, key: String, providers: RegionWatchProviders) {
+        self.watch_providers.insert(
+            key,
+            CachedItem {
+                data: providers,
+                timestamp: Instant::now(),
+            },
+        );
+        self.maybe_cleanup();
+    }
+
+    fn insert_provider_list(&self, key: String, providers: Vec<WatchProviderEntry>) {
+        self.provider_lists.insert(
+            key,
+            CachedItem {
+                data: providers,
+                timestamp: Instant::now(),
+            },
+        );
+        self.maybe_cleanup();
+    }
+
+    fn insert_genres(&self, key: String, genres: Vec<Genre>) {
+        self.genres.insert(
+            key,
+            CachedItem {
+                data: genres,
+                timestamp: Instant::now(),
+            },
+        );
+        self.maybe_cleanup();
+    }
+
+    fn insert_external_ids(&self, key: String, ids: ExternalIds) {
+        self.external_ids.insert(
+            key,
+            CachedItem {
+                data: ids,
+                timestamp: Instant::now(),
+            },
+        );
+        self.maybe_cleanup();
+    }
+
     async fn save_cache_to_disk(&self) -> Result<()> {
         let cache_file = CacheFile {
             tv_details: self
@@ -188,6 +354,26 @@ impl CacheManager {
                 .iter()
                 .map(|r| (r.key().clone(), r.value().clone()))
                 .collect(),
+            watch_providers: self
+                .watch_providers
+                .iter()
+                .map(|r| (r.key().clone(), r.value().clone()))
+                .collect(),
+            provider_lists: self
+                .provider_lists
+                .iter()
+                .map(|r| (r.key().clone(), r.value().clone()))
+                .collect(),
+            genres: self
+                .genres
+                .iter()
+                .map(|r| (r.key().clone(), r.value().clone()))
+                .collect(),
+            external_ids: self
+                .external_ids
+                .iter()
+                .map(|r| (r.key().clone(), r.value().clone()))
+                .collect(),
         };
 
         let cache_path = PathBuf::from("cache");
@@ -220,6 +406,26 @@ impl CacheManager {
                             manager.omdb_ratings.insert(key, item);
                         }
                     }
+                    for (key, item) in cache_file.watch_providers {
+                        if item.timestamp.elapsed() < CACHE_TTL {
+                            manager.watch_providers.insert(key, item);
+                        }
+                    }
+                    for (key, item) in cache_file.provider_lists {
+                        if item.timestamp.elapsed() < CACHE_TTL {
+                            manager.provider_lists.insert(key, item);
+                        }
+                    }
+                    for (key, item) in cache_file.genres {
+                        if item.timestamp.elapsed() < CACHE_TTL {
+                            manager.genres.insert(key, item);
+                        }
+                    }
+                    for (key, item) in cache_file.external_ids {
+                        if item.timestamp.elapsed() < CACHE_TTL {
+                            manager.external_ids.insert(key, item);
+                        }
+                    }
                     debug!("Cache loaded successfully");
                 }
                 Err(e) => {
@@ -242,23 +448,83 @@ pub async fn get_cached_tv_details(id: i32) -> Option<TVShowDetails> {
     get_cache().await.get_tv_details(id).map(|item| item.data)
 }
 
-pub async fn get_cached_omdb_rating(title: &str, year: &str) -> Option<OMDBResponse> {
-    let key = format!("{}_{}", title, year);
-    get_cache()
-        .await
-        .get_omdb_rating(&key)
-        .map(|item| item.data)
+pub async fn get_cached_omdb_rating(key: &str) -> Option<OMDBResponse> {
+    get_cache().await.get_omdb_rating(key).map(|item| item.data)
 }
 
 pub async fn cache_tv_details(id: i32, details: TVShowDetails) {
     get_cache().await.insert_tv_details(id, details);
 }
 
-pub async fn cache_omdb_rating(title: &str, year: &str, rating: OMDBResponse) {
-    let key = format!("{}_{}", title, year);
+pub async fn cache_omdb_rating(key: String, rating: OMDBResponse) {
     get_cache().await.insert_omdb_rating(key, rating);
 }
 
+pub async fn get_cached_watch_providers(
+    media_type: &str,
+    id: i32,
+    region: &str,
+) -> Option<RegionWatchProviders> {
+    let key = format!("{}_{}_{}", media_type, id, region);
+    get_cache()
+        .await
+        .get_watch_providers(&key)
+        .map(|item| item.data)
+}
+
+pub async fn cache_watch_providers(
+    media_type: &str,
+    id: i32,
+    region: &str,
+    providers: RegionWatchProviders,
+) {
+    let key = format!("{}_{}_{}", media_type, id, region);
+    get_cache().await.insert_watch_providers(key, providers);
+}
+
+pub async fn get_cached_provider_list(
+    media_type: &str,
+    region: &str,
+) -> Option<Vec<WatchProviderEntry>> {
+    let key = format!("{}_{}", media_type, region);
+    get_cache()
+        .await
+        .get_provider_list(&key)
+        .map(|item| item.data)
+}
+
+pub async fn cache_provider_list(
+    media_type: &str,
+    region: &str,
+    providers: Vec<WatchProviderEntry>,
+) {
+    let key = format!("{}_{}", media_type, region);
+    get_cache().await.insert_provider_list(key, providers);
+}
+
+pub async fn get_cached_genres(media_type: &str, language: &str) -> Option<Vec<Genre>> {
+    let key = format!("{}_{}", media_type, language);
+    get_cache().await.get_genres(&key).map(|item| item.data)
+}
+
+pub async fn cache_genres(media_type: &str, language: &str, genres: Vec<Genre>) {
+    let key = format!("{}_{}", media_type, language);
+    get_cache().await.insert_genres(key, genres);
+}
+
+pub async fn get_cached_external_ids(media_type: &str, id: i32) -> Option<ExternalIds> {
+    let key = format!("{}_{}", media_type, id);
+    get_cache()
+        .await
+        .get_external_ids(&key)
+        .map(|item| item.data)
+}
+
+pub async fn cache_external_ids(media_type: &str, id: i32, ids: ExternalIds) {
+    let key = format!("{}_{}", media_type, id);
+    get_cache().await.insert_external_ids(key, ids);
+}
+
 pub async fn save_cache() -> Result<()> {
     get_cache().await.save_cache_to_disk().await
 }
@@ -16,6 +16,20 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+impl Error {
+    /// The status an upstream API answered with, for errors from `handle_response`.
+    pub fn upstream_status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api(msg) => msg
+                .split_whitespace()
+                .nth(2)
+                .and_then(|s| s.parse::<u16>().ok())
+                .and_then(|code| StatusCode::from_u16(code).ok()),
+            _ => None,
+        }
+    }
+}
+
 static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
     Client::builder()
         .timeout(Duration::from_secs(10))
@@ -72,7 +86,7 @@ impl ApiClient {
                     return Ok(response);
                 }
                 Err(e) => {
-                    if let Some(status) = self.get_status_code(&e) {
+                    if let Some(status) = e.upstream_status() {
                         if !self.should_retry(status) {
                             return Err(e);
                         }
@@ -107,17 +121,6 @@ impl ApiClient {
         )
     }
 
-    fn get_status_code(&self, error: &Error) -> Option<StatusCode> {
-        match error {
-            Error::Api(msg) => msg
-                .split_whitespace()
-                .nth(2)
-                .and_then(|s| s.parse::<u16>().ok())
-                .and_then(|code| StatusCode::from_u16(code).ok()),
-            _ => None,
-        }
-    }
-
     // ****************************************************
     // Generic request methods
     // ****************************************************
@@ -138,12 +141,13 @@ impl ApiClient {
         .await
     }
 
-    // Generic POST request with body
+    // Generic POST request with body, plus one extra header when the API needs it
     pub async fn post<T: Serialize, R: DeserializeOwned>(
         &self,
         url: &str,
         body: &T,
         api_key: Option<&Secret<String>>,
+        extra_header: Option<(&str, &str)>,
     ) -> Result<R> {
         debug!("Making POST request to: {}", url);
 
@@ -157,6 +161,9 @@ impl ApiClient {
         if let Some(key) = api_key {
             request = request.header("X-Api-Key", key.expose_secret());
         }
+        if let Some((name, value)) = extra_header {
+            request = request.header(name, value);
+        }
 
         let response = request.send().await?;
 
@@ -173,34 +180,30 @@ impl ApiClient {
         endpoint: &str,
         api_key: &Secret<String>,
     ) -> Result<T> {
-        let separator = if endpoint.contains('?') { "&" } else { "?" };
-        let url = format!(
-            "https://api.themoviedb.org/3/{}{}{}&language=en-US",
-            endpoint,
-            separator,
-            format!("api_key={}", api_key.expose_secret())
-        );
+        let mut url = reqwest::Url::parse(&format!("https://api.themoviedb.org/3/{}", endpoint))
+            .map_err(|e| Error::Request(format!("Invalid TMDB endpoint {}: {}", endpoint, e)))?;
+        url.query_pairs_mut()
+            .append_pair("api_key", api_key.expose_secret());
+
         debug!(
             "Making TMDB request to: {}",
-            url.replace(api_key.expose_secret(), "API_KEY")
+            url.as_str().replace(api_key.expose_secret(), "API_KEY")
         );
-        self.get(&url).await
+        self.get(url.as_str()).await
     }
 
     // Helper for OMDB specific requests
     pub async fn omdb_get<T: DeserializeOwned>(
         &self,
-        title: &str,
-        year: &str,
+        params: &[(&str, &str)],
         api_key: &Secret<String>,
     ) -> Result<T> {
-        let url = format!(
-            "http://www.omdbapi.com/?apikey={}&t={}&y={}",
-            api_key.expose_secret(),
-            urlencoding::encode(title),
-            year
-        );
-        self.get(&url).await
+        let mut url = reqwest::Url::parse("http://www.omdbapi.com/")
+            .map_err(|e| Error::Request(format!("Invalid OMDB URL: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("apikey", api_key.expose_secret())
+            .extend_pairs(params);
+        self.get(url.as_str()).await
     }
 
     pub async fn jellyseerr_get<R>(
@@ -225,16 +228,26 @@ impl ApiClient {
         self.handle_response(response).await
     }
 
-    // Helper for Jellyseerr specific requests
+    // Helper for Jellyseerr specific requests. With a user ID Jellyseerr acts on
+    // behalf of that user instead of the API key's owner.
     pub async fn jellyseerr_post<T: Serialize, R: DeserializeOwned>(
         &self,
         endpoint: &str,
         body: &T,
         api_key: &Secret<String>,
         base_url: &str,
+        user_id: Option<i32>,
     ) -> Result<R> {
         let url = format!("{}/api/v1/{}", base_url, endpoint);
-        self.post(&url, body, Some(api_key)).await
+        let user_id = user_id.map(|id| id.to_string());
+
+        self.post(
+            &url,
+            body,
+            Some(api_key),
+            user_id.as_deref().map(|id| ("X-Api-User", id)),
+        )
+        .await
     }
 
     // Generic response handler
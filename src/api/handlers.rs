@@ -1,94 +1,515 @@
-use crate::security::csrf::validate_csrf_token;
-use crate::{Error, Result};
+use crate::security::auth::CurrentUser;
+use crate::security::headers::CspNonce;
+use crate::{AuthMode, Error, ReleaseSource, Result};
 use askama::Template;
 use axum::{
-    extract::{Path, State},
-    response::{Html, IntoResponse, Response},
+    extract::{Form, Path, Query, State},
+    response::{AppendHeaders, Html, IntoResponse, Redirect, Response},
     Json,
 };
 use chrono::{DateTime, Utc};
-use http::HeaderMap;
+use http::{header::SET_COOKIE, HeaderMap, StatusCode};
 use serde::Deserialize;
 use serde_json::json;
-use tracing::{error, info};
+use std::collections::HashSet;
+use tracing::{error, info, warn};
 
-use super::{jellyseerr, tmdb};
-use crate::security;
+use super::jellyseerr::{self, LoginMethod};
+use super::{tasks, tmdb};
 use crate::AppState;
 
+/// Seasons as sent by the client: `"all"`, an empty list (also "all") or
+/// explicit season numbers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SeasonSelection {
+    Keyword(String),
+    Numbers(Vec<i32>),
+}
+
 #[derive(Deserialize)]
 pub struct MediaRequest {
-    seasons: Option<Vec<i32>>,
+    seasons: Option<SeasonSelection>,
+}
+
+/// Checks the requested seasons against TMDB and expands "all" into the show's
+/// regular seasons, so Jellyseerr always receives an explicit list.
+fn resolve_seasons(
+    selection: Option<SeasonSelection>,
+    details: &tmdb::TVShowDetails,
+    taken: &HashSet<i32>,
+) -> Result<Vec<i32>> {
+    let requested = match selection {
+        None => Vec::new(),
+        Some(SeasonSelection::Numbers(numbers)) => numbers,
+        Some(SeasonSelection::Keyword(keyword)) if keyword.eq_ignore_ascii_case("all") => {
+            Vec::new()
+        }
+        Some(SeasonSelection::Keyword(keyword)) => {
+            return Err(Error::BadRequest(format!(
+                "Invalid season selection: {}",
+                keyword
+            )));
+        }
+    };
+
+    if requested.is_empty() {
+        let mut all: Vec<i32> = details
+            .seasons
+            .iter()
+            .map(|season| season.season_number)
+            .filter(|number| *number > 0 && !taken.contains(number))
+            .collect();
+        // Shows that only have specials still need something to request
+        if all.is_empty() {
+            all = details
+                .seasons
+                .iter()
+                .map(|season| season.season_number)
+                .filter(|number| !taken.contains(number))
+                .collect();
+        }
+        if all.is_empty() {
+            return Err(Error::BadRequest(
+                "Every season of this show is already requested or available".into(),
+            ));
+        }
+        return Ok(all);
+    }
+
+    if let Some(unknown) = requested
+        .iter()
+        .find(|number| !details.has_season(**number))
+    {
+        return Err(Error::BadRequest(format!(
+            "Season {} does not exist for this show",
+            unknown
+        )));
+    }
+
+    if let Some(already) = requested.iter().find(|number| taken.contains(number)) {
+        return Err(Error::BadRequest(format!(
+            "Season {} is already requested or available",
+            already
+        )));
+    }
+
+    let mut seasons = requested;
+    seasons.sort_unstable();
+    seasons.dedup();
+    Ok(seasons)
+}
+
+/// Comma-separated genre IDs or names, e.g. `?exclude_genres=Reality,10767`.
+#[derive(Deserialize)]
+pub struct ReleaseFilter {
+    include_genres: Option<String>,
+    exclude_genres: Option<String>,
+}
+
+impl ReleaseFilter {
+    fn tokens(value: &Option<String>) -> Vec<&str> {
+        value
+            .as_deref()
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|token| !token.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn matches(&self, release: &tmdb::Release) -> bool {
+        let include = Self::tokens(&self.include_genres);
+        let exclude = Self::tokens(&self.exclude_genres);
+
+        (include.is_empty() || include.iter().any(|token| release.has_genre(token)))
+            && !exclude.iter().any(|token| release.has_genre(token))
+    }
+}
+
+struct FeedTab {
+    id: String,
+    name: String,
 }
 
 #[derive(Template)]
 #[template(path = "../templates/index.html")]
 struct IndexTemplate {
+    feeds: Vec<FeedTab>,
+    active_feed: String,
     releases: String,
     last_update: DateTime<Utc>,
     csrf_token: String,
+    username: Option<String>,
+    csp_nonce: String,
 }
 
-pub async fn index(State(state): State<AppState>) -> Html<String> {
-    let releases = state.releases.read().await;
-    let last_update = state.last_update.read().await;
+#[derive(Template)]
+#[template(path = "../templates/login.html")]
+struct LoginTemplate {
+    error: Option<String>,
+    /// Native accounts have no Jellyseerr/Jellyfin choice to make.
+    show_methods: bool,
+    csrf_token: String,
+    csp_nonce: String,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    username: String,
+    password: String,
+    #[serde(default)]
+    method: LoginMethod,
+    #[serde(default)]
+    csrf_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct LogoutForm {
+    #[serde(default)]
+    csrf_token: String,
+}
+
+pub async fn index(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    nonce: CspNonce,
+    user: Option<CurrentUser>,
+) -> Response {
+    let feed_id = state.default_feed_id().to_string();
+    render_feed(&headers, &state, &nonce, &feed_id, user).await
+}
+
+pub async fn feed_index(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    nonce: CspNonce,
+    user: Option<CurrentUser>,
+    Path(feed_id): Path<String>,
+) -> Response {
+    render_feed(&headers, &state, &nonce, &feed_id, user).await
+}
+
+fn render_login(
+    headers: &HeaderMap,
+    state: &AppState,
+    nonce: &CspNonce,
+    status: StatusCode,
+    error: Option<String>,
+) -> Response {
+    let (csrf_binding, csrf_cookie) = state.csrf.binding(headers);
+    let template = LoginTemplate {
+        error,
+        show_methods: state.config.auth.mode == AuthMode::Jellyseerr,
+        csrf_token: state.csrf.issue(&csrf_binding),
+        csp_nonce: nonce.0.clone(),
+    };
+    match template.render() {
+        Ok(html) => (
+            status,
+            AppendHeaders(csrf_cookie.map(|cookie| (SET_COOKIE, cookie))),
+            Html(html),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to render template: {}", e);
+            Html("Template Renderer Error".to_string()).into_response()
+        }
+    }
+}
+
+pub async fn login_page(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    nonce: CspNonce,
+) -> Response {
+    if matches!(state.config.auth.mode, AuthMode::None | AuthMode::Proxy) {
+        return Redirect::to("/").into_response();
+    }
+    render_login(&headers, &state, &nonce, StatusCode::OK, None)
+}
+
+pub async fn login(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    nonce: CspNonce,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    if matches!(state.config.auth.mode, AuthMode::None | AuthMode::Proxy) {
+        return Redirect::to("/").into_response();
+    }
+
+    // Otherwise another site could sign visitors into an account of its choosing
+    if let Err(e) = state.csrf.verify_form(&headers, &form.csrf_token) {
+        warn!("Rejected sign-in for {}: {}", form.username, e);
+        return render_login(
+            &headers,
+            &state,
+            &nonce,
+            StatusCode::FORBIDDEN,
+            Some("The sign-in form expired, please try again".into()),
+        );
+    }
+
+    let session = match state.config.auth.mode {
+        AuthMode::Local => state
+            .users
+            .authenticate(&form.username, &form.password)
+            .await
+            .map(|account| {
+                let user = CurrentUser {
+                    username: account.username,
+                    email: None,
+                    jellyseerr_id: None,
+                    jellyseerr_permissions: None,
+                    role: account.role,
+                };
+                (user, account.session_version)
+            }),
+        _ => jellyseerr::authenticate(&state.config, form.method, &form.username, &form.password)
+            .await
+            .map(|user| (user, 0)),
+    };
+
+    match session {
+        Ok((user, version)) => (
+            AppendHeaders([
+                (SET_COOKIE, state.sessions.issue(&user, version)),
+                (SET_COOKIE, state.csrf.clear()),
+            ]),
+            Redirect::to("/"),
+        )
+            .into_response(),
+        Err(e) => {
+            warn!("Failed sign-in for {}: {}", form.username, e);
+            render_login(
+                &headers,
+                &state,
+                &nonce,
+                StatusCode::UNAUTHORIZED,
+                Some("Invalid username or password".into()),
+            )
+        }
+    }
+}
+
+pub async fn logout(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Form(form): Form<LogoutForm>,
+) -> Response {
+    if let Err(e) = state.csrf.verify_form(&headers, &form.csrf_token) {
+        warn!("Rejected sign-out: {}", e);
+        return (
+            StatusCode::FORBIDDEN,
+            Html("Invalid CSRF token".to_string()),
+        )
+            .into_response();
+    }
+
+    // Clearing the cookie alone would leave copies of it valid until they expire
+    if let Err(e) = state.sessions.revoke(&headers).await {
+        error!("Failed to revoke session: {}", e);
+    }
+
+    (
+        AppendHeaders([
+            (SET_COOKIE, state.sessions.clear()),
+            (SET_COOKIE, state.csrf.clear()),
+        ]),
+        Redirect::to("/login"),
+    )
+        .into_response()
+}
+
+async fn render_feed(
+    headers: &HeaderMap,
+    state: &AppState,
+    nonce: &CspNonce,
+    feed_id: &str,
+    user: Option<CurrentUser>,
+) -> Response {
+    let Some(feed) = state.feed(feed_id) else {
+        return (StatusCode::NOT_FOUND, Html("Feed not found".to_string())).into_response();
+    };
+
+    let releases = feed.releases.read().await;
+    let last_update = feed.last_update.read().await;
 
     let releases_json = serde_json::to_string(&*releases).unwrap_or_else(|_| "[]".to_string());
+    let (csrf_binding, csrf_cookie) = state.csrf.binding(headers);
+
+    // In configured order, with the names admins may have changed since
+    let mut tabs = Vec::with_capacity(state.config.feeds.len());
+    for feed in &state.config.feeds {
+        if let Some(tab) = state.feed(&feed.id) {
+            tabs.push(FeedTab {
+                id: feed.id.clone(),
+                name: tab.config.read().await.name.clone(),
+            });
+        }
+    }
 
     let template = IndexTemplate {
+        feeds: tabs,
+        active_feed: feed_id.to_string(),
         releases: releases_json,
         last_update: *last_update,
-        csrf_token: security::csrf::generate_csrf_token(),
+        csrf_token: state.csrf.issue(&csrf_binding),
+        username: user.map(|user| user.username),
+        csp_nonce: nonce.0.clone(),
     };
 
     match template.render() {
-        Ok(html) => Html(html),
+        Ok(html) => (
+            AppendHeaders(csrf_cookie.map(|cookie| (SET_COOKIE, cookie))),
+            Html(html),
+        )
+            .into_response(),
         Err(e) => {
             error!("Failed to render template: {}", e);
-            Html("Template Renderer Error".to_string())
+            Html("Template Renderer Error".to_string()).into_response()
         }
     }
 }
 
+pub async fn releases(
+    State(state): State<AppState>,
+    Query(filter): Query<ReleaseFilter>,
+) -> Response {
+    let feed_id = state.default_feed_id().to_string();
+    list_releases(&state, &feed_id, &filter).await
+}
+
+pub async fn feed_releases(
+    State(state): State<AppState>,
+    Path(feed_id): Path<String>,
+    Query(filter): Query<ReleaseFilter>,
+) -> Response {
+    list_releases(&state, &feed_id, &filter).await
+}
+
+/// A fresh CSRF token for pages that outlived the one they were rendered with.
+pub async fn csrf_token(headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let (binding, cookie) = state.csrf.binding(&headers);
+    let body = Json(json!({
+        "success": true,
+        "token": state.csrf.issue(&binding),
+    }));
+
+    match cookie {
+        Some(cookie) => (AppendHeaders([(SET_COOKIE, cookie)]), body).into_response(),
+        None => body.into_response(),
+    }
+}
+
+async fn list_releases(state: &AppState, feed_id: &str, filter: &ReleaseFilter) -> Response {
+    let Some(feed) = state.feed(feed_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "success": false,
+                "error": format!("Unknown feed: {}", feed_id)
+            })),
+        )
+            .into_response();
+    };
+
+    let releases: Vec<_> = feed
+        .releases
+        .read()
+        .await
+        .iter()
+        .filter(|release| filter.matches(release))
+        .cloned()
+        .collect();
+    let last_update = *feed.last_update.read().await;
+
+    Json(json!({
+        "success": true,
+        "releases": releases,
+        "lastUpdate": last_update.to_rfc3339(),
+    }))
+    .into_response()
+}
+
 pub async fn add_to_jellyseerr(
-    headers: HeaderMap,
     State(state): State<AppState>,
+    user: Option<CurrentUser>,
     Path((media_type, id)): Path<(String, i32)>,
     Json(payload): Json<MediaRequest>,
 ) -> impl IntoResponse {
     // Handle all potential errors and convert to IntoResponse
-    match process_media_request(headers, &state, &media_type, id, payload).await {
+    match process_media_request(&state, user.as_ref(), &media_type, id, payload).await {
         Ok(response) => response,
         Err(e) => {
             error!("Error adding to Jellyseerr: {}", e);
-            Json(json!({
-                "success": false,
-                "error": e.to_string()
-            }))
-            .into_response()
+            let status = match e {
+                Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+                Error::Forbidden(_) => StatusCode::FORBIDDEN,
+                _ => StatusCode::OK,
+            };
+            (
+                status,
+                Json(json!({
+                    "success": false,
+                    "error": e.to_string()
+                })),
+            )
+                .into_response()
         }
     }
 }
 
 async fn process_media_request(
-    headers: HeaderMap,
     state: &AppState,
+    user: Option<&CurrentUser>,
     media_type: &str,
     id: i32,
     payload: MediaRequest,
 ) -> Result<Response> {
-    validate_csrf_token(&headers)?;
+    if let Some(user) = user.filter(|user| !user.can_request(media_type)) {
+        return Err(Error::Forbidden(format!(
+            "{} is not allowed to request {}",
+            user.username,
+            if media_type == "tv" {
+                "TV shows"
+            } else {
+                "movies"
+            }
+        )));
+    }
 
     let seasons = match media_type {
-        "tv" => payload.seasons,
+        "tv" => {
+            let details = match state.config.discovery.source {
+                ReleaseSource::Tmdb => tmdb::fetch_tv_details(&state.config, id).await?,
+                ReleaseSource::Jellyseerr => {
+                    jellyseerr::fetch_tv_details(&state.config, id).await?
+                }
+            };
+            let taken = jellyseerr::taken_seasons(&state.config, id).await?;
+            Some(resolve_seasons(payload.seasons, &details, &taken)?)
+        }
         "movie" => None,
-        _ => return Err(Error::Config(format!("Invalid media type: {}", media_type))),
+        _ => {
+            return Err(Error::BadRequest(format!(
+                "Invalid media type: {}",
+                media_type
+            )))
+        }
     };
 
-    jellyseerr::request_media(&state.config, id, media_type, seasons).await?;
+    let user_id = jellyseerr::resolve_user_id(&state.config, user).await?;
+    jellyseerr::request_media(&state.config, id, media_type, seasons.clone(), user_id).await?;
 
-    let mut releases = state.releases.write().await;
-    releases.retain(|release| !(release.id == id && release.media_type == media_type));
+    match &seasons {
+        Some(seasons) => state.remove_seasons(id, seasons).await,
+        None => state.remove_release(id, media_type).await,
+    }
 
     Ok(Json(json!({
         "success": true,
@@ -98,8 +519,13 @@ async fn process_media_request(
     .into_response())
 }
 
-pub async fn refresh(headers: HeaderMap, State(state): State<AppState>) -> impl IntoResponse {
-    match update_release_list(headers, &state).await {
+pub async fn refresh(State(state): State<AppState>) -> impl IntoResponse {
+    let feed_id = state.default_feed_id().to_string();
+    refresh_feed(State(state), Path(feed_id)).await
+}
+
+pub async fn refresh_feed(State(state): State<AppState>, Path(feed_id): Path<String>) -> Response {
+    match update_release_list(&state, &feed_id).await {
         Ok(response) => response,
         Err(e) => {
             error!("Error refreshing: {}", e);
@@ -112,21 +538,29 @@ pub async fn refresh(headers: HeaderMap, State(state): State<AppState>) -> impl
     }
 }
 
-async fn update_release_list(headers: HeaderMap, state: &AppState) -> Result<Response> {
-    validate_csrf_token(&headers)?;
-
-    info!("Manual refresh triggered");
+async fn update_release_list(state: &AppState, feed_id: &str) -> Result<Response> {
+    let Some(feed_state) = state.feed(feed_id) else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "success": false,
+                "error": format!("Unknown feed: {}", feed_id)
+            })),
+        )
+            .into_response());
+    };
 
-    let new_releases = tmdb::fetch_latest_releases(&state.config).await?;
+    info!("Manual refresh triggered for feed {}", feed_id);
 
-    let filtered_releases = jellyseerr::filter_requested_media(&state.config, new_releases).await?;
+    let feed = feed_state.config.read().await.clone();
+    let filtered_releases = tasks::fetch_feed_releases(state, &feed).await?;
 
-    let mut releases = state.releases.write().await;
+    let mut releases = feed_state.releases.write().await;
     *releases = filtered_releases.clone();
-    let mut last_update = state.last_update.write().await;
+    let mut last_update = feed_state.last_update.write().await;
     *last_update = Utc::now();
 
-    info!("Manual refresh successful");
+    info!("Manual refresh of feed {} successful", feed_id);
 
     Ok(Json(json!({
         "success": true,
@@ -137,11 +571,10 @@ async fn update_release_list(headers: HeaderMap, state: &AppState) -> Result<Res
 }
 
 pub async fn hide_media(
-    headers: HeaderMap,
     State(state): State<AppState>,
     Path((media_type, id)): Path<(String, i32)>,
 ) -> impl IntoResponse {
-    match remove_media_from_view(headers, &state, &media_type, id).await {
+    match remove_media_from_view(&state, &media_type, id).await {
         Ok(response) => response,
         Err(e) => {
             error!("Error hiding media: {}", e);
@@ -154,16 +587,8 @@ pub async fn hide_media(
     }
 }
 
-async fn remove_media_from_view(
-    headers: HeaderMap,
-    state: &AppState,
-    media_type: &str,
-    id: i32,
-) -> Result<Response> {
-    validate_csrf_token(&headers)?;
-
-    let mut releases = state.releases.write().await;
-    releases.retain(|release| !(release.id == id && release.media_type == media_type));
+async fn remove_media_from_view(state: &AppState, media_type: &str, id: i32) -> Result<Response> {
+    state.remove_release(id, media_type).await;
 
     info!("Hidden media {}/{} from view", media_type, id);
 
@@ -173,3 +598,67 @@ async fn remove_media_from_view(
     }))
     .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::security::csrf::{CSRF_COOKIE, CSRF_HEADER};
+    use crate::{init_router, testing, AuthMode};
+    use axum::body::Body;
+    use http::{header::COOKIE, Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn refreshing_an_unknown_feed_is_not_found() {
+        let state = testing::state(AuthMode::None);
+        let token = state.csrf.issue("browser");
+        let response = init_router(state)
+            .oneshot(
+                Request::post("/api/refresh/missing")
+                    .header(COOKIE, format!("{}=browser", CSRF_COOKIE))
+                    .header(CSRF_HEADER, token)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    fn form(uri: &str, body: &str) -> Request<Body> {
+        Request::post(uri)
+            .header(COOKIE, format!("{}=browser", CSRF_COOKIE))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sign_in_and_out_need_a_csrf_token() {
+        let state = testing::state(AuthMode::Local);
+        let forged = testing::state(AuthMode::Local).csrf.issue("someone-else");
+
+        for token in ["", forged.as_str()] {
+            let response = init_router(state.clone())
+                .oneshot(form(
+                    "/login",
+                    &format!("username=admin&password=secret&csrf_token={}", token),
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{:?}", token);
+
+            let response = init_router(state.clone())
+                .oneshot(form("/logout", &format!("csrf_token={}", token)))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{:?}", token);
+        }
+
+        let token = state.csrf.issue("browser");
+        let response = init_router(state)
+            .oneshot(form("/logout", &format!("csrf_token={}", token)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    }
+}
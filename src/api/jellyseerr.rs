@@ -1,30 +1,369 @@
+use crate::api::cache;
 use crate::api::client::ApiClient;
-use crate::api::tmdb::Release;
-use crate::{AppConfig, Result};
+use crate::api::tmdb::{
+    self, DiscoverQuery, ExternalIds, Genre, RegionWatchProviders, Release, Season, TMDBResponse,
+    TMDBResult, TVShowDetails, TitleDetails, WatchProviderEntry,
+};
+use crate::security::auth::{CurrentUser, Role};
+use crate::{AppConfig, DiscoveryConfig, Error, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
 use reqwest;
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use tracing::{debug, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{debug, error, info, warn};
+
+const REQUEST_STATUS_DECLINED: i32 = 3;
+
+const PAGE_SIZE: usize = 100;
+// Incremental syncs cannot see deleted entries unless the total changes, nor
+// status changes that leave `updatedAt` alone, so everything is refetched this often
+const FULL_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const USER_ID_TTL: Duration = Duration::from_secs(60 * 60);
+// How long a signed-in user's permissions are trusted before asking Jellyseerr again
+const PERMISSIONS_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Where Jellyseerr stands on a title or season. Unknown (1) and deleted (7)
+/// media have no status, since both can be requested again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaStatus {
+    Pending,
+    Processing,
+    PartiallyAvailable,
+    Available,
+    Blacklisted,
+}
+
+impl MediaStatus {
+    pub const ALL: [MediaStatus; 5] = [
+        MediaStatus::Pending,
+        MediaStatus::Processing,
+        MediaStatus::PartiallyAvailable,
+        MediaStatus::Available,
+        MediaStatus::Blacklisted,
+    ];
+
+    fn from_code(code: i32) -> Option<Self> {
+        match code {
+            2 => Some(MediaStatus::Pending),
+            3 => Some(MediaStatus::Processing),
+            4 => Some(MediaStatus::PartiallyAvailable),
+            5 => Some(MediaStatus::Available),
+            6 => Some(MediaStatus::Blacklisted),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
-struct JellyseerrMediaResponse {
-    results: Vec<RequestResult>,
+struct PageInfo {
+    results: usize,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
+struct PageResponse<T> {
+    #[serde(rename = "pageInfo")]
+    page_info: PageInfo,
+    results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct SeasonStatus {
+    #[serde(rename = "seasonNumber")]
+    season_number: i32,
+    #[serde(default)]
+    status: i32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
 struct RequestResult {
+    id: i32,
     #[serde(rename = "type")]
     request_type: String,
+    #[serde(rename = "updatedAt", default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    status: i32,
+    #[serde(default)]
+    seasons: Vec<SeasonStatus>,
     media: MediaInfo,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
+struct MediaRequestInfo {
+    #[serde(default)]
+    status: i32,
+    #[serde(default)]
+    seasons: Vec<SeasonStatus>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
 struct MediaInfo {
+    #[serde(default)]
+    id: i32,
     #[serde(rename = "mediaType")] // Match exact field name from JSON
     media_type: String,
     #[serde(rename = "tmdbId")] // Match exact field name from JSON
     tmdb_id: i32,
+    #[serde(rename = "updatedAt", default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    status: i32,
+    #[serde(default)]
+    seasons: Vec<SeasonStatus>,
+    #[serde(default)]
+    requests: Vec<MediaRequestInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscoverResponse {
+    page: u32,
+    total_pages: u32,
+    results: Vec<DiscoverResult>,
+}
+
+/// A TMDB discover result as proxied by Jellyseerr, with its media info attached.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscoverResult {
+    id: i32,
+    title: Option<String>,
+    name: Option<String>,
+    release_date: Option<String>,
+    first_air_date: Option<String>,
+    #[serde(default)]
+    vote_average: f32,
+    #[serde(default)]
+    vote_count: i32,
+    poster_path: Option<String>,
+    overview: Option<String>,
+    #[serde(default)]
+    genre_ids: Vec<i32>,
+    media_info: Option<MediaInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProviderDetails {
+    id: i32,
+    name: String,
+    logo_path: Option<String>,
+    #[serde(default)]
+    display_priority: i32,
+}
+
+impl From<ProviderDetails> for WatchProviderEntry {
+    fn from(provider: ProviderDetails) -> Self {
+        Self {
+            provider_id: provider.id,
+            provider_name: provider.name,
+            logo_path: provider.logo_path,
+            display_priority: provider.display_priority,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionProviders {
+    iso_3166_1: String,
+    link: Option<String>,
+    #[serde(default)]
+    flatrate: Vec<ProviderDetails>,
+    #[serde(default)]
+    buy: Vec<ProviderDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DetailsSeason {
+    season_number: i32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    episode_count: i32,
+    air_date: Option<String>,
+    poster_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct DetailsExternalIds {
+    imdb_id: Option<String>,
+}
+
+/// The parts of Jellyseerr's `movie/{id}` and `tv/{id}` responses we use.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DetailsResponse {
+    #[serde(default)]
+    number_of_seasons: i32,
+    #[serde(default)]
+    seasons: Vec<DetailsSeason>,
+    #[serde(default)]
+    external_ids: DetailsExternalIds,
+    #[serde(default)]
+    watch_providers: Vec<RegionProviders>,
+    media_info: Option<MediaInfo>,
+}
+
+impl DetailsResponse {
+    fn tv_details(&self) -> TVShowDetails {
+        TVShowDetails {
+            number_of_seasons: self.number_of_seasons,
+            seasons: self
+                .seasons
+                .iter()
+                .map(|season| Season {
+                    season_number: season.season_number,
+                    name: season.name.clone(),
+                    episode_count: season.episode_count,
+                    air_date: season.air_date.clone(),
+                    poster_path: season.poster_path.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn region_providers(&mut self, region: &str) -> RegionWatchProviders {
+        let Some(index) = self
+            .watch_providers
+            .iter()
+            .position(|providers| providers.iso_3166_1 == region)
+        else {
+            return RegionWatchProviders::default();
+        };
+        let providers = self.watch_providers.swap_remove(index);
+
+        RegionWatchProviders {
+            link: providers.link,
+            flatrate: providers.flatrate.into_iter().map(Into::into).collect(),
+            buy: providers.buy.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn external_ids(&self) -> ExternalIds {
+        ExternalIds {
+            imdb_id: self
+                .external_ids
+                .imdb_id
+                .clone()
+                .filter(|imdb_id| !imdb_id.is_empty()),
+        }
+    }
+}
+
+/// Entries of a Jellyseerr list endpoint that can be synced by modification time.
+trait Synced: DeserializeOwned + Clone {
+    fn id(&self) -> i32;
+    fn updated_at(&self) -> Option<DateTime<Utc>>;
+}
+
+impl Synced for RequestResult {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+}
+
+impl Synced for MediaInfo {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+}
+
+/// Every entry of a Jellyseerr list seen so far, kept between refreshes so that
+/// only entries modified since the last sync need to be fetched.
+struct SyncCache<T> {
+    entries: HashMap<i32, T>,
+    last_full_sync: Option<Instant>,
+}
+
+impl<T> Default for SyncCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            last_full_sync: None,
+        }
+    }
+}
+
+impl<T: Synced> SyncCache<T> {
+    fn watermark(&self) -> Option<DateTime<Utc>> {
+        self.entries.values().filter_map(Synced::updated_at).max()
+    }
+
+    fn needs_full_sync(&self) -> bool {
+        self.last_full_sync
+            .is_none_or(|synced| synced.elapsed() > FULL_SYNC_INTERVAL)
+    }
+}
+
+static REQUEST_CACHE: Lazy<Mutex<SyncCache<RequestResult>>> =
+    Lazy::new(|| Mutex::new(SyncCache::default()));
+
+// Also holds titles Jellyfin has that were never requested through Jellyseerr
+static MEDIA_CACHE: Lazy<Mutex<SyncCache<MediaInfo>>> =
+    Lazy::new(|| Mutex::new(SyncCache::default()));
+
+/// What Jellyseerr already has or has been asked for, per title.
+#[derive(Debug, Default)]
+struct MediaState {
+    status: Option<MediaStatus>,
+    seasons: HashMap<i32, MediaStatus>,
+}
+
+impl MediaState {
+    fn add_media_info(&mut self, media: &MediaInfo) {
+        if let Some(status) = MediaStatus::from_code(media.status) {
+            self.status = Some(status);
+        }
+        for season in &media.seasons {
+            if let Some(status) = MediaStatus::from_code(season.status) {
+                self.seasons.insert(season.season_number, status);
+            }
+        }
+        for request in &media.requests {
+            self.add_request(request.status, &request.seasons);
+        }
+    }
+
+    /// Seasons the feeds leave out for the configured `hidden_statuses`.
+    fn hidden_seasons(&self, hidden: &[MediaStatus]) -> HashSet<i32> {
+        self.seasons
+            .iter()
+            .filter(|(_, status)| is_hidden(hidden, Some(**status)))
+            .map(|(number, _)| *number)
+            .collect()
+    }
+
+    /// Requests only count as pending for titles and seasons Jellyseerr has no
+    /// better status for.
+    fn add_request(&mut self, status: i32, seasons: &[SeasonStatus]) {
+        if status == REQUEST_STATUS_DECLINED {
+            return;
+        }
+        self.status.get_or_insert(MediaStatus::Pending);
+        for season in seasons {
+            self.seasons
+                .entry(season.season_number)
+                .or_insert(MediaStatus::Pending);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -49,9 +388,13 @@ pub async fn request_media(
     tmdb_id: i32,
     media_type: &str,
     season: Option<Vec<i32>>,
+    user_id: Option<i32>,
 ) -> Result<()> {
-    debug!("Requesting media: type={}, id={}", media_type, tmdb_id);
-    let client = ApiClient::new(&config);
+    debug!(
+        "Requesting media: type={}, id={}, user={:?}",
+        media_type, tmdb_id, user_id
+    );
+    let client = ApiClient::new(config);
 
     // Create the request body
     let body = match media_type {
@@ -72,12 +415,13 @@ pub async fn request_media(
     };
     debug!("Sending request to Jellyseerr: {:?}", body);
 
-    let _: () = client
+    let _: serde_json::Value = client
         .jellyseerr_post(
             "request",
             &body,
             &config.jellyseerr_api_key,
             &config.jellyseerr_url,
+            user_id,
         )
         .await?;
 
@@ -89,30 +433,825 @@ pub async fn request_media(
     Ok(())
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JellyseerrUser {
+    id: i32,
+    email: Option<String>,
+    #[serde(default)]
+    permissions: u32,
+}
+
+/// A user lookup and when it was made.
+struct Resolved<T> {
+    value: T,
+    resolved: Instant,
+}
+
+// Misses are kept too, so unmatched users do not page through /user on every request
+static USER_IDS: Lazy<Mutex<HashMap<String, Resolved<Option<i32>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static USER_PERMISSIONS: Lazy<Mutex<HashMap<i32, Resolved<u32>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Looks up the Jellyseerr account registered with an email address. Names are
+/// never matched: anyone can pick a username that belongs to a Jellyseerr admin.
+async fn find_user_id(config: &AppConfig, email: &str) -> Result<Option<i32>> {
+    let key = email.to_lowercase();
+    if let Some(cached) = USER_IDS.lock().await.get(&key) {
+        if cached.resolved.elapsed() < USER_ID_TTL {
+            return Ok(cached.value);
+        }
+    }
+
+    let client = ApiClient::new(config);
+    let mut skip = 0;
+    let id = loop {
+        let data: PageResponse<JellyseerrUser> = client
+            .jellyseerr_get(
+                &format!("user?take={}&skip={}", PAGE_SIZE, skip),
+                &config.jellyseerr_api_key,
+                &config.jellyseerr_url,
+            )
+            .await?;
+
+        if let Some(found) = data.results.iter().find(|candidate| {
+            candidate
+                .email
+                .as_deref()
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(email))
+        }) {
+            break Some(found.id);
+        }
+
+        skip += data.results.len();
+        if data.results.is_empty() || skip >= data.page_info.results {
+            break None;
+        }
+    };
+
+    USER_IDS.lock().await.insert(
+        key,
+        Resolved {
+            value: id,
+            resolved: Instant::now(),
+        },
+    );
+    Ok(id)
+}
+
+/// A Jellyseerr user's current permission bits, so that changes made in
+/// Jellyseerr reach sessions signed in before them. `None` once Jellyseerr no
+/// longer has the user. When Jellyseerr cannot be asked, `known` stands in
+/// until the next check is due, instead of asking again on every request.
+pub async fn user_permissions(
+    config: &AppConfig,
+    id: i32,
+    known: Option<u32>,
+) -> Result<Option<u32>> {
+    if let Some(cached) = USER_PERMISSIONS.lock().await.get(&id) {
+        if cached.resolved.elapsed() < PERMISSIONS_TTL {
+            return Ok(Some(cached.value));
+        }
+    }
+
+    let client = ApiClient::new(config);
+    let result: Result<JellyseerrUser> = client
+        .jellyseerr_get(
+            &format!("user/{}", id),
+            &config.jellyseerr_api_key,
+            &config.jellyseerr_url,
+        )
+        .await;
+
+    let permissions = match result {
+        Ok(user) => user.permissions,
+        Err(e) if e.upstream_status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            USER_PERMISSIONS.lock().await.remove(&id);
+            return Ok(None);
+        }
+        Err(e) => {
+            if let Some(permissions) = known {
+                USER_PERMISSIONS.lock().await.insert(
+                    id,
+                    Resolved {
+                        value: permissions,
+                        resolved: Instant::now(),
+                    },
+                );
+            }
+            return Err(e);
+        }
+    };
+
+    USER_PERMISSIONS.lock().await.insert(
+        id,
+        Resolved {
+            value: permissions,
+            resolved: Instant::now(),
+        },
+    );
+    Ok(Some(permissions))
+}
+
+/// Which Jellyseerr sign-in endpoint credentials are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMethod {
+    /// A Jellyseerr local account, signed in with its email address.
+    #[default]
+    Jellyseerr,
+    Jellyfin,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthenticatedUser {
+    id: i32,
+    email: Option<String>,
+    username: Option<String>,
+    jellyfin_username: Option<String>,
+    display_name: Option<String>,
+    #[serde(default)]
+    permissions: u32,
+}
+
+/// Checks credentials against Jellyseerr and returns the account they belong to.
+pub async fn authenticate(
+    config: &AppConfig,
+    method: LoginMethod,
+    username: &str,
+    password: &str,
+) -> Result<CurrentUser> {
+    let client = ApiClient::new(config);
+    let (endpoint, body) = match method {
+        LoginMethod::Jellyseerr => (
+            "auth/local",
+            serde_json::json!({ "email": username, "password": password }),
+        ),
+        LoginMethod::Jellyfin => (
+            "auth/jellyfin",
+            serde_json::json!({ "username": username, "password": password }),
+        ),
+    };
+
+    let user: AuthenticatedUser = client
+        .post(
+            &format!("{}/api/v1/{}", config.jellyseerr_url, endpoint),
+            &body,
+            None,
+            None,
+        )
+        .await
+        .map_err(|e| {
+            debug!("Jellyseerr rejected sign-in for {}: {}", username, e);
+            Error::Unauthorized("Invalid username or password".into())
+        })?;
+
+    info!("{} signed in through {:?}", username, method);
+
+    Ok(CurrentUser {
+        username: user
+            .jellyfin_username
+            .or(user.username)
+            .or(user.display_name)
+            .or_else(|| user.email.clone())
+            .unwrap_or_else(|| username.to_string()),
+        email: user.email,
+        jellyseerr_id: Some(user.id),
+        jellyseerr_permissions: Some(user.permissions),
+        role: Role::from_jellyseerr_permissions(user.permissions),
+    })
+}
+
+/// Picks the Jellyseerr user a request should be attributed to: the user's own
+/// Jellyseerr ID, the configured mapping, the Jellyseerr account with the same
+/// email, then `default_user_id`. `None` means the API key's owner.
+///
+/// Only Jellyseerr sign-ins and the trusted proxy's email header set an email,
+/// so it is never something the user picked themselves.
+pub async fn resolve_user_id(
+    config: &AppConfig,
+    user: Option<&CurrentUser>,
+) -> Result<Option<i32>> {
+    let Some(user) = user else {
+        return Ok(config.jellyseerr_default_user);
+    };
+
+    if let Some(id) = user.jellyseerr_id {
+        return Ok(Some(id));
+    }
+    if let Some(id) = config.jellyseerr_users.get(&user.username) {
+        return Ok(Some(*id));
+    }
+    if let Some(email) = &user.email {
+        if let Some(id) = find_user_id(config, email).await? {
+            debug!("Matched {} to Jellyseerr user {}", user.username, id);
+            return Ok(Some(id));
+        }
+    }
+
+    warn!(
+        "No Jellyseerr user found for {}, falling back to the default user",
+        user.username
+    );
+    Ok(config.jellyseerr_default_user)
+}
+
+/// Season numbers of a show that its Jellyseerr status keeps off the feeds,
+/// so requests are checked against the same seasons the page offers.
+pub async fn taken_seasons(config: &AppConfig, tmdb_id: i32) -> Result<HashSet<i32>> {
+    let client = ApiClient::new(config);
+
+    let data: DetailsResponse = client
+        .jellyseerr_get(
+            &format!("tv/{}", tmdb_id),
+            &config.jellyseerr_api_key,
+            &config.jellyseerr_url,
+        )
+        .await?;
+
+    let mut state = MediaState::default();
+    if let Some(media) = &data.media_info {
+        state.add_media_info(media);
+    }
+
+    Ok(state.hidden_seasons(&config.jellyseerr_hidden_statuses))
+}
+
+/// Pages through a list endpoint newest-modified first. With a watermark it stops
+/// at the first entry not modified since then. Returns the entries and the total
+/// Jellyseerr reports.
+async fn fetch_modified<T: Synced>(
+    client: &ApiClient,
+    config: &AppConfig,
+    endpoint: &str,
+    watermark: Option<DateTime<Utc>>,
+) -> Result<(Vec<T>, usize)> {
+    let mut entries = Vec::new();
+    let mut skip = 0;
+
+    loop {
+        let data: PageResponse<T> = client
+            .jellyseerr_get(
+                &format!(
+                    "{}?take={}&skip={}&sort=modified",
+                    endpoint, PAGE_SIZE, skip
+                ),
+                &config.jellyseerr_api_key,
+                &config.jellyseerr_url,
+            )
+            .await?;
+
+        let total = data.page_info.results;
+        let page_len = data.results.len();
+        skip += page_len;
+
+        for entry in data.results {
+            // Entries without a timestamp sort below every watermark
+            if watermark.is_some_and(|watermark| entry.updated_at() < Some(watermark)) {
+                return Ok((entries, total));
+            }
+            entries.push(entry);
+        }
+
+        if page_len == 0 || skip >= total {
+            return Ok((entries, total));
+        }
+    }
+}
+
+async fn sync_list<T: Synced>(
+    config: &AppConfig,
+    cache: &Mutex<SyncCache<T>>,
+    endpoint: &str,
+) -> Result<Vec<T>> {
+    let client = ApiClient::new(config);
+    let mut cache = cache.lock().await;
+
+    if !cache.needs_full_sync() {
+        let watermark = cache.watermark();
+        let (updated, total) = fetch_modified::<T>(&client, config, endpoint, watermark).await?;
+        debug!(
+            "Fetched {} updated Jellyseerr {} entries",
+            updated.len(),
+            endpoint
+        );
+
+        for entry in updated {
+            cache.entries.insert(entry.id(), entry);
+        }
+
+        if cache.entries.len() == total {
+            return Ok(cache.entries.values().cloned().collect());
+        }
+        debug!(
+            "Cached {} Jellyseerr {} entries but {} exist, resyncing",
+            cache.entries.len(),
+            endpoint,
+            total
+        );
+    }
+
+    let (entries, total) = fetch_modified::<T>(&client, config, endpoint, None).await?;
+    debug!(
+        "Fetched all {} of {} Jellyseerr {} entries",
+        entries.len(),
+        total,
+        endpoint
+    );
+
+    cache.entries = entries
+        .into_iter()
+        .map(|entry| (entry.id(), entry))
+        .collect();
+    cache.last_full_sync = Some(Instant::now());
+
+    Ok(cache.entries.values().cloned().collect())
+}
+
+fn is_hidden(hidden: &[MediaStatus], status: Option<MediaStatus>) -> bool {
+    status.is_some_and(|status| hidden.contains(&status))
+}
+
+/// Drops titles and seasons whose Jellyseerr status is hidden by the
+/// configuration and tags the rest with their status.
+fn apply_media_states(
+    releases: Vec<Release>,
+    states: &HashMap<(String, i32), MediaState>,
+    hidden: &[MediaStatus],
+) -> Vec<Release> {
+    let is_hidden = |status: Option<MediaStatus>| is_hidden(hidden, status);
+
+    releases
+        .into_iter()
+        .filter_map(|mut release| {
+            let Some(state) = states.get(&(release.media_type.clone(), release.id)) else {
+                return Some(release);
+            };
+            release.media_status = state.status;
+
+            // Movies, and shows we have no season list for, go on the title's status alone
+            if release.media_type != "tv" || release.seasons.is_empty() {
+                return (!is_hidden(state.status)).then_some(release);
+            }
+
+            // Blacklisting covers the whole show, and without season statuses the
+            // title's status is all we know about each season
+            let title_only =
+                state.seasons.is_empty() && state.status != Some(MediaStatus::PartiallyAvailable);
+            if (title_only || state.status == Some(MediaStatus::Blacklisted))
+                && is_hidden(state.status)
+            {
+                return None;
+            }
+
+            for season in &mut release.seasons {
+                season.media_status = state.seasons.get(&season.season_number).copied();
+            }
+            release
+                .seasons
+                .retain(|season| !is_hidden(season.media_status));
+
+            // Keep partially handled shows while a regular season is still listed
+            release
+                .seasons
+                .iter()
+                .any(|season| season.season_number > 0)
+                .then_some(release)
+        })
+        .collect()
+}
+
 pub async fn filter_requested_media(
     config: &AppConfig,
     releases: Vec<Release>,
 ) -> Result<Vec<Release>> {
-    let client = ApiClient::new(&config);
+    let (requests, media) = tokio::try_join!(
+        sync_list(config, &REQUEST_CACHE, "request"),
+        sync_list(config, &MEDIA_CACHE, "media")
+    )?;
+
+    let mut states: HashMap<(String, i32), MediaState> = HashMap::new();
+    for info in &media {
+        states
+            .entry((info.media_type.clone(), info.tmdb_id))
+            .or_default()
+            .add_media_info(info);
+    }
+    for request in &requests {
+        let state = states
+            .entry((request.media.media_type.clone(), request.media.tmdb_id))
+            .or_default();
+        state.add_media_info(&request.media);
+        state.add_request(request.status, &request.seasons);
+    }
+
+    Ok(apply_media_states(
+        releases,
+        &states,
+        &config.jellyseerr_hidden_statuses,
+    ))
+}
 
-    let data: JellyseerrMediaResponse = client
+/// Fetches a title's details from Jellyseerr and stores its seasons, external IDs
+/// and regional watch providers in the caches the TMDB lookups use.
+async fn fetch_title_details(
+    config: &AppConfig,
+    media_type: &str,
+    id: i32,
+    region: &str,
+) -> Result<TitleDetails> {
+    let client = ApiClient::new(config);
+    let mut details: DetailsResponse = client
         .jellyseerr_get(
-            "request?take=50",
+            &format!(
+                "{}/{}?language={}",
+                media_type,
+                id,
+                urlencoding::encode(&config.discovery.language)
+            ),
             &config.jellyseerr_api_key,
             &config.jellyseerr_url,
         )
         .await?;
 
-    let requested_media: std::collections::HashSet<(String, i32)> = data
-        .results
-        .iter()
-        .map(|request| (request.media.media_type.clone(), request.media.tmdb_id))
-        .collect();
+    let tv = (media_type == "tv").then(|| details.tv_details());
+    let providers = details.region_providers(region);
+    let external_ids = details.external_ids();
 
-    let filtered_releases = releases
-        .into_iter()
-        .filter(|release| !requested_media.contains(&(release.media_type.clone(), release.id)))
-        .collect();
+    if let Some(tv) = &tv {
+        cache::cache_tv_details(id, tv.clone()).await;
+    }
+    cache::cache_watch_providers(media_type, id, region, providers.clone()).await;
+    cache::cache_external_ids(media_type, id, external_ids.clone()).await;
+
+    Ok(TitleDetails {
+        tv,
+        providers,
+        external_ids,
+    })
+}
+
+async fn title_details(
+    config: &AppConfig,
+    media_type: &str,
+    id: i32,
+    region: &str,
+) -> Result<TitleDetails> {
+    let tv = match media_type {
+        "tv" => cache::get_cached_tv_details(id)
+            .await
+            .filter(|details| !details.seasons.is_empty() || details.number_of_seasons == 0),
+        _ => None,
+    };
+    let providers = cache::get_cached_watch_providers(media_type, id, region).await;
+    let external_ids = cache::get_cached_external_ids(media_type, id).await;
+
+    match (providers, external_ids) {
+        (Some(providers), Some(external_ids)) if media_type != "tv" || tv.is_some() => {
+            debug!("Cache hit for Jellyseerr details: {}/{}", media_type, id);
+            Ok(TitleDetails {
+                tv,
+                providers,
+                external_ids,
+            })
+        }
+        _ => {
+            debug!(
+                "Cache miss for Jellyseerr details {}/{}, fetching from API",
+                media_type, id
+            );
+            fetch_title_details(config, media_type, id, region).await
+        }
+    }
+}
+
+/// Season metadata for a show, looked up through Jellyseerr instead of TMDB.
+pub async fn fetch_tv_details(config: &AppConfig, tv_id: i32) -> Result<TVShowDetails> {
+    let details = title_details(config, "tv", tv_id, &config.discovery.region).await?;
+    Ok(details.tv.unwrap_or_else(|| TVShowDetails {
+        number_of_seasons: 0,
+        seasons: Vec::new(),
+    }))
+}
+
+/// Lists the watch providers Jellyseerr knows for a media type in a region.
+pub async fn fetch_provider_list(
+    config: &AppConfig,
+    media_type: &str,
+    region: &str,
+) -> Result<Vec<WatchProviderEntry>> {
+    if let Some(cached) = cache::get_cached_provider_list(media_type, region).await {
+        debug!("Cache hit for {} provider list in {}", media_type, region);
+        return Ok(cached);
+    }
+
+    let client = ApiClient::new(config);
+    let providers: Vec<ProviderDetails> = client
+        .jellyseerr_get(
+            &format!(
+                "watchproviders/{}?watchRegion={}",
+                if media_type == "movie" {
+                    "movies"
+                } else {
+                    "tv"
+                },
+                urlencoding::encode(region)
+            ),
+            &config.jellyseerr_api_key,
+            &config.jellyseerr_url,
+        )
+        .await?;
+    let providers: Vec<WatchProviderEntry> = providers.into_iter().map(Into::into).collect();
+
+    cache::cache_provider_list(media_type, region, providers.clone()).await;
+
+    Ok(providers)
+}
 
-    Ok(filtered_releases)
+async fn genre_names(config: &AppConfig, media_type: &str, language: &str) -> HashMap<i32, String> {
+    let genres = match cache::get_cached_genres(media_type, language).await {
+        Some(cached) => Ok(cached),
+        None => {
+            let client = ApiClient::new(config);
+            let genres = client
+                .jellyseerr_get::<Vec<Genre>>(
+                    &format!(
+                        "genres/{}?language={}",
+                        media_type,
+                        urlencoding::encode(language)
+                    ),
+                    &config.jellyseerr_api_key,
+                    &config.jellyseerr_url,
+                )
+                .await;
+            if let Ok(genres) = &genres {
+                cache::cache_genres(media_type, language, genres.clone()).await;
+            }
+            genres
+        }
+    };
+
+    match genres {
+        Ok(genres) => genres
+            .into_iter()
+            .map(|genre| (genre.id, genre.name))
+            .collect(),
+        Err(e) => {
+            error!(
+                "Failed to fetch {} genres from Jellyseerr: {}",
+                media_type, e
+            );
+            HashMap::new()
+        }
+    }
+}
+
+impl From<DiscoverResult> for TMDBResult {
+    fn from(item: DiscoverResult) -> Self {
+        Self {
+            id: item.id,
+            title: item.title,
+            name: item.name,
+            release_date: item.release_date,
+            first_air_date: item.first_air_date,
+            media_type: String::new(),
+            vote_average: item.vote_average,
+            vote_count: item.vote_count,
+            poster_path: item.poster_path,
+            overview: item.overview,
+            genre_ids: item.genre_ids,
+        }
+    }
+}
+
+/// Discovers through Jellyseerr's TMDB proxy. Results carry their media info,
+/// which is kept to filter the releases once they are built.
+struct Jellyseerr<'a> {
+    config: &'a AppConfig,
+    client: ApiClient,
+    states: std::sync::Mutex<HashMap<(String, i32), MediaState>>,
+}
+
+impl tmdb::DiscoverySource for Jellyseerr<'_> {
+    async fn discover(&self, query: &DiscoverQuery) -> Result<TMDBResponse> {
+        let data: DiscoverResponse = self
+            .client
+            .jellyseerr_get(
+                &query.to_jellyseerr_endpoint(),
+                &self.config.jellyseerr_api_key,
+                &self.config.jellyseerr_url,
+            )
+            .await?;
+
+        let mut states = self.states.lock().expect("lock poisoned");
+        for item in &data.results {
+            if let Some(media) = &item.media_info {
+                states
+                    .entry((query.media_type().to_string(), item.id))
+                    .or_default()
+                    .add_media_info(media);
+            }
+        }
+
+        Ok(TMDBResponse {
+            page: data.page,
+            total_pages: data.total_pages,
+            results: data.results.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    async fn genre_names(&self, media_type: &str, language: &str) -> HashMap<i32, String> {
+        genre_names(self.config, media_type, language).await
+    }
+
+    async fn title_details(&self, media_type: &str, id: i32, region: &str) -> TitleDetails {
+        title_details(self.config, media_type, id, region)
+            .await
+            .unwrap_or_else(|e| {
+                error!(
+                    "Failed to fetch Jellyseerr details for {}/{}: {}",
+                    media_type, id, e
+                );
+                TitleDetails::default()
+            })
+    }
+}
+
+/// Builds the release list from Jellyseerr's discover endpoints instead of TMDB.
+pub async fn fetch_latest_releases(
+    config: &AppConfig,
+    discovery: &DiscoveryConfig,
+    enrichment: &Semaphore,
+) -> Result<Vec<Release>> {
+    let source = Jellyseerr {
+        config,
+        client: ApiClient::new(config),
+        states: Default::default(),
+    };
+    let releases = tmdb::discover_releases(&source, config, discovery, enrichment).await?;
+    let states = source.states.into_inner().expect("lock poisoned");
+
+    Ok(apply_media_states(
+        releases,
+        &states,
+        &config.jellyseerr_hidden_statuses,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::tmdb::ReleaseSeason;
+
+    fn release(media_type: &str, id: i32, seasons: &[i32]) -> Release {
+        Release {
+            id,
+            title: format!("{} {}", media_type, id),
+            release_date: "2025-01-01".into(),
+            media_type: media_type.into(),
+            vote_average: 7.0,
+            vote_count: 10,
+            poster_url: String::new(),
+            tmdb_url: String::new(),
+            number_of_seasons: None,
+            seasons: seasons
+                .iter()
+                .map(|number| ReleaseSeason {
+                    season_number: *number,
+                    name: format!("Season {}", number),
+                    episode_count: 10,
+                    air_date: None,
+                    poster_url: None,
+                    media_status: None,
+                })
+                .collect(),
+            overview: None,
+            imdb_rating: None,
+            metascore: None,
+            rotten_tomatoes: None,
+            providers: Vec::new(),
+            genre_ids: Vec::new(),
+            genres: Vec::new(),
+            imdb_id: None,
+            imdb_url: None,
+            media_status: None,
+        }
+    }
+
+    fn media(media_type: &str, tmdb_id: i32, status: i32, seasons: &[(i32, i32)]) -> MediaInfo {
+        MediaInfo {
+            id: tmdb_id,
+            media_type: media_type.into(),
+            tmdb_id,
+            updated_at: None,
+            status,
+            seasons: seasons
+                .iter()
+                .map(|(season_number, status)| SeasonStatus {
+                    season_number: *season_number,
+                    status: *status,
+                })
+                .collect(),
+            requests: Vec::new(),
+        }
+    }
+
+    fn states(media: &[MediaInfo]) -> HashMap<(String, i32), MediaState> {
+        let mut states: HashMap<(String, i32), MediaState> = HashMap::new();
+        for info in media {
+            states
+                .entry((info.media_type.clone(), info.tmdb_id))
+                .or_default()
+                .add_media_info(info);
+        }
+        states
+    }
+
+    #[test]
+    fn hides_library_titles_and_keeps_missing_seasons() {
+        let states = states(&[
+            media("movie", 1, 5, &[]),
+            media("tv", 2, 4, &[(1, 5), (2, 1)]),
+            media("tv", 3, 5, &[(1, 5)]),
+        ]);
+        let releases = vec![
+            release("movie", 1, &[]),
+            release("tv", 2, &[0, 1, 2]),
+            release("tv", 3, &[1]),
+            release("movie", 4, &[]),
+        ];
+
+        let filtered = apply_media_states(releases, &states, &MediaStatus::ALL);
+
+        assert_eq!(
+            filtered
+                .iter()
+                .map(|release| (release.media_type.as_str(), release.id))
+                .collect::<Vec<_>>(),
+            vec![("tv", 2), ("movie", 4)]
+        );
+        let show = &filtered[0];
+        assert_eq!(show.media_status, Some(MediaStatus::PartiallyAvailable));
+        assert_eq!(
+            show.seasons
+                .iter()
+                .map(|season| season.season_number)
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert_eq!(filtered[1].media_status, None);
+    }
+
+    #[test]
+    fn badges_titles_whose_status_is_not_hidden() {
+        let states = states(&[media("movie", 1, 2, &[]), media("tv", 2, 3, &[(1, 3)])]);
+        let releases = vec![release("movie", 1, &[]), release("tv", 2, &[1])];
+
+        let filtered = apply_media_states(releases, &states, &[MediaStatus::Available]);
+
+        assert_eq!(filtered[0].media_status, Some(MediaStatus::Pending));
+        assert_eq!(filtered[1].media_status, Some(MediaStatus::Processing));
+        assert_eq!(
+            filtered[1].seasons[0].media_status,
+            Some(MediaStatus::Processing)
+        );
+    }
+
+    #[test]
+    fn requests_may_take_the_seasons_the_feed_offers() {
+        let states = states(&[media("tv", 2, 4, &[(1, 5), (2, 2), (3, 1)])]);
+        let hidden = [MediaStatus::Available];
+
+        let offered = apply_media_states(vec![release("tv", 2, &[1, 2, 3])], &states, &hidden);
+        let taken = states[&("tv".to_string(), 2)].hidden_seasons(&hidden);
+
+        assert_eq!(taken, HashSet::from([1]));
+        assert!(offered[0]
+            .seasons
+            .iter()
+            .all(|season| !taken.contains(&season.season_number)));
+        assert_eq!(offered[0].seasons.len(), 2);
+    }
+
+    #[test]
+    fn watermarks_compare_timestamps_not_strings() {
+        let mut whole = media("movie", 1, 0, &[]);
+        let mut fractional = media("movie", 2, 0, &[]);
+        let mut undated = media("movie", 3, 0, &[]);
+        whole.updated_at = serde_json::from_str(r#""2024-05-01T10:00:00Z""#).unwrap();
+        fractional.updated_at = serde_json::from_str(r#""2024-05-01T10:00:00.500Z""#).unwrap();
+        undated.updated_at = None;
+
+        let cache = SyncCache {
+            entries: [whole, fractional.clone(), undated]
+                .into_iter()
+                .map(|entry| (entry.id, entry))
+                .collect(),
+            last_full_sync: None,
+        };
+        assert_eq!(cache.watermark(), fractional.updated_at);
+    }
 }
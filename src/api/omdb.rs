@@ -58,29 +58,67 @@ impl OMDBResponse {
     pub fn get_metascore(&self) -> Option<String> {
         Self::clean_rating(&self.metascore)
     }
+
+    /// OMDB answers unknown titles with an empty record rather than an error.
+    pub fn has_ratings(&self) -> bool {
+        self.get_imdb_rating().is_some()
+            || self.get_metascore().is_some()
+            || self
+                .ratings
+                .as_ref()
+                .is_some_and(|ratings| !ratings.is_empty())
+    }
+
+    pub fn get_rotten_tomatoes(&self) -> Option<String> {
+        self.ratings.as_ref().and_then(|ratings| {
+            ratings
+                .iter()
+                .find(|rating| rating.source == "Rotten Tomatoes")
+                .map(|rating| rating.value.to_string())
+        })
+    }
 }
 
+/// Looks up ratings by IMDb ID when TMDB knows it, falling back to a title and
+/// year search otherwise. `media_type` is the TMDB one ("movie" or "tv").
 pub async fn fetch_ratings(
     config: &crate::AppConfig,
+    media_type: &str,
+    imdb_id: Option<&str>,
     title: &str,
     year: &str,
 ) -> Result<OMDBResponse> {
+    let omdb_type = if media_type == "tv" {
+        "series"
+    } else {
+        "movie"
+    };
+
+    let cache_key = match (imdb_id, media_type) {
+        (Some(id), _) => id.to_string(),
+        (None, "tv") => format!("series_{}_{}", title, year),
+        (None, _) => format!("{}_{}", title, year),
+    };
+
     // Check cache first
-    if let Some(cached) = crate::api::cache::get_cached_omdb_rating(title, year).await {
-        debug!("Cache hit for OMDB: {} ({})", title, year);
+    if let Some(cached) = crate::api::cache::get_cached_omdb_rating(&cache_key).await {
+        debug!("Cache hit for OMDB: {} ({})", title, cache_key);
         return Ok(cached);
     }
 
     debug!(
         "Cache miss for OMDB, fetching from API: {} ({})",
-        title, year
+        title, cache_key
     );
 
-    let client = ApiClient::new(&config);
+    let client = ApiClient::new(config);
 
-    debug!("Fetching OMDB data for: {} ({})", title, year);
+    let params: Vec<(&str, &str)> = match imdb_id {
+        Some(id) => vec![("i", id)],
+        None => vec![("t", title), ("y", year), ("type", omdb_type)],
+    };
 
-    let data: OMDBResponse = client.omdb_get(title, year, &config.omdb_api_key).await?;
+    let data: OMDBResponse = client.omdb_get(&params, &config.omdb_api_key).await?;
 
     let cleaned_data = OMDBResponse {
         imdb_rating: data.get_imdb_rating(),
@@ -88,7 +126,7 @@ pub async fn fetch_ratings(
         ratings: data.ratings,
     };
 
-    crate::api::cache::cache_omdb_rating(title, year, cleaned_data.clone()).await;
+    crate::api::cache::cache_omdb_rating(cache_key, cleaned_data.clone()).await;
 
     Ok(cleaned_data)
 }
@@ -1,38 +1,58 @@
 use chrono::Utc;
 use tokio::time::{interval, Duration};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use super::tmdb;
 use crate::api::jellyseerr;
-use crate::AppState;
+use crate::{AppState, FeedConfig, Release, ReleaseSource, Result};
 
-pub async fn refresh_releases(state: AppState, refresh_interval: Duration) {
-    let mut interval = interval(refresh_interval);
+/// Builds a feed's releases from the configured source, without anything
+/// Jellyseerr already has.
+pub async fn fetch_feed_releases(state: &AppState, feed: &FeedConfig) -> Result<Vec<Release>> {
+    let config = &state.config;
+    match config.discovery.source {
+        ReleaseSource::Tmdb => {
+            let new_releases =
+                tmdb::fetch_latest_releases(config, &feed.discovery, &state.enrichment).await?;
+            jellyseerr::filter_requested_media(config, new_releases).await
+        }
+        ReleaseSource::Jellyseerr => {
+            jellyseerr::fetch_latest_releases(config, &feed.discovery, &state.enrichment).await
+        }
+    }
+}
+
+/// Refreshes a feed on its interval. Each refresh reads the feed's current
+/// settings, so changes made by admins apply from the next one.
+pub async fn refresh_releases(state: AppState, feed_id: String, refresh_interval: u64) {
+    let mut interval = interval(Duration::from_secs(refresh_interval));
 
     loop {
         interval.tick().await;
-        info!("Refreshing release");
+        info!("Refreshing feed {}", feed_id);
+
+        let Some(feed_state) = state.feed(&feed_id) else {
+            warn!("Feed {} is not registered, stopping refresh task", feed_id);
+            return;
+        };
+        let feed = feed_state.config.read().await.clone();
 
         // Fetch data outside of any locks
-        let fetch_result = async {
-            let new_releases = tmdb::fetch_latest_releases(&state.config).await?;
-            jellyseerr::filter_requested_media(&state.config, new_releases).await
-        }
-        .await;
+        let fetch_result = fetch_feed_releases(&state, &feed).await;
 
         match fetch_result {
             Ok(filtered_releases) => {
                 // Minimize time holding both locks
-                let mut releases = state.releases.write().await;
-                let mut last_update = state.last_update.write().await;
+                let mut releases = feed_state.releases.write().await;
+                let mut last_update = feed_state.last_update.write().await;
                 *releases = filtered_releases;
                 *last_update = Utc::now();
                 drop(last_update);
                 drop(releases);
-                info!("Successfully refreshed releases");
+                info!("Successfully refreshed feed {}", feed_id);
             }
             Err(e) => {
-                error!("Failed to refresh releases: {}", e);
+                error!("Failed to refresh feed {}: {}", feed_id, e);
             }
         }
     }
@@ -1,11 +1,35 @@
 use crate::api::client::ApiClient;
-use crate::api::omdb;
+use crate::api::jellyseerr::{self, MediaStatus};
+use crate::api::omdb::{self, OMDBResponse};
 use crate::AppConfig;
-use crate::Result;
+use crate::DiscoveryConfig;
+use crate::{Error, ProviderRef, ReleaseSource, Result};
+use chrono::{NaiveDate, Utc};
+use futures::future;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::future::Future;
+use tokio::sync::Semaphore;
 use tracing::{debug, error};
 
+/// Where every poster, still and logo comes from.
+pub const IMAGE_HOST: &str = "https://image.tmdb.org";
+
+/// Shown for titles without a poster. Served locally so the CSP needs no
+/// extra image host.
+pub const POSTER_PLACEHOLDER: &str = "/static/img/poster-placeholder.svg";
+
+/// Full URL of a TMDB image at the given size, e.g. `w500`.
+pub fn image_url(size: &str, path: &str) -> String {
+    format!("{}/t/p/{}{}", IMAGE_HOST, size, path)
+}
+
+pub fn poster_url(path: Option<&str>) -> String {
+    path.map(|path| image_url("w500", path))
+        .unwrap_or_else(|| POSTER_PLACEHOLDER.to_string())
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TMDBResult {
     pub id: i32,
@@ -19,10 +43,14 @@ pub struct TMDBResult {
     pub vote_count: i32,
     pub poster_path: Option<String>,
     pub overview: Option<String>,
+    #[serde(default)]
+    pub genre_ids: Vec<i32>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct TMDBResponse {
+    pub page: u32,
+    pub total_pages: u32,
     pub results: Vec<TMDBResult>,
 }
 
@@ -45,9 +73,139 @@ impl fmt::Display for TMDBError {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Season {
+    pub season_number: i32,
+    pub name: String,
+    #[serde(default)]
+    pub episode_count: i32,
+    pub air_date: Option<String>,
+    pub poster_path: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TVShowDetails {
     pub number_of_seasons: i32,
+    #[serde(default)]
+    pub seasons: Vec<Season>,
+}
+
+impl TVShowDetails {
+    pub fn has_season(&self, season_number: i32) -> bool {
+        self.seasons
+            .iter()
+            .any(|season| season.season_number == season_number)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReleaseSeason {
+    pub season_number: i32,
+    pub name: String,
+    pub episode_count: i32,
+    pub air_date: Option<String>,
+    pub poster_url: Option<String>,
+    pub media_status: Option<MediaStatus>,
+}
+
+impl From<&Season> for ReleaseSeason {
+    fn from(season: &Season) -> Self {
+        Self {
+            season_number: season.season_number,
+            name: season.name.clone(),
+            episode_count: season.episode_count,
+            air_date: season.air_date.clone(),
+            poster_url: season
+                .poster_path
+                .as_ref()
+                .map(|path| image_url("w185", path)),
+            media_status: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct WatchProviderEntry {
+    pub provider_id: i32,
+    pub provider_name: String,
+    pub logo_path: Option<String>,
+    #[serde(default)]
+    pub display_priority: i32,
+}
+
+/// Where a title can be watched in a single region, as returned by TMDB.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RegionWatchProviders {
+    pub link: Option<String>,
+    #[serde(default)]
+    pub flatrate: Vec<WatchProviderEntry>,
+    #[serde(default)]
+    pub free: Vec<WatchProviderEntry>,
+    #[serde(default)]
+    pub ads: Vec<WatchProviderEntry>,
+    #[serde(default)]
+    pub rent: Vec<WatchProviderEntry>,
+    #[serde(default)]
+    pub buy: Vec<WatchProviderEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ExternalIds {
+    pub imdb_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Genre {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenreListResponse {
+    genres: Vec<Genre>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WatchProviderListResponse {
+    results: Vec<WatchProviderEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WatchProvidersResponse {
+    #[serde(default)]
+    results: HashMap<String, RegionWatchProviders>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReleaseProvider {
+    pub id: i32,
+    pub name: String,
+    pub logo_url: Option<String>,
+    pub monetization_type: String,
+}
+
+impl RegionWatchProviders {
+    pub fn to_release_providers(&self) -> Vec<ReleaseProvider> {
+        [
+            ("flatrate", &self.flatrate),
+            ("free", &self.free),
+            ("ads", &self.ads),
+            ("rent", &self.rent),
+            ("buy", &self.buy),
+        ]
+        .into_iter()
+        .flat_map(|(monetization_type, entries)| {
+            let mut entries = entries.clone();
+            entries.sort_by_key(|entry| entry.display_priority);
+            entries.into_iter().map(move |entry| ReleaseProvider {
+                id: entry.provider_id,
+                name: entry.provider_name,
+                logo_url: entry.logo_path.map(|path| image_url("w92", &path)),
+                monetization_type: monetization_type.to_string(),
+            })
+        })
+        .collect()
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -61,136 +219,434 @@ pub struct Release {
     pub poster_url: String,
     pub tmdb_url: String,
     pub number_of_seasons: Option<i32>,
+    pub seasons: Vec<ReleaseSeason>,
     pub overview: Option<String>,
     pub imdb_rating: Option<String>,
     pub metascore: Option<String>,
     pub rotten_tomatoes: Option<String>,
+    pub providers: Vec<ReleaseProvider>,
+    pub genre_ids: Vec<i32>,
+    pub genres: Vec<String>,
+    pub imdb_id: Option<String>,
+    pub imdb_url: Option<String>,
+    pub media_status: Option<MediaStatus>,
 }
 
-pub async fn fetch_latest_releases(config: &AppConfig) -> Result<Vec<Release>> {
-    let client = ApiClient::new(&config);
-    let mut all_releases = Vec::new();
+impl Release {
+    /// Matches a genre filter token, given either as a TMDB genre ID or a name.
+    pub fn has_genre(&self, token: &str) -> bool {
+        match token.trim().parse::<i32>() {
+            Ok(id) => self.genre_ids.contains(&id),
+            Err(_) => self
+                .genres
+                .iter()
+                .any(|genre| genre.eq_ignore_ascii_case(token.trim())),
+        }
+    }
+}
 
-    // Fetch movies
-    let movie_data: TMDBResponse = client
-            .tmdb_get(
-                "discover/movie?sort_by=release_date.desc&with_watch_providers=8|9|337|1899|350|15|619|283&watch_region=US&vote_count.gte=1&vote_average.gte=1&page=1",
-                &config.tmdb_api_key,
-            )
-            .await?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    PopularityDesc,
+    ReleaseDateDesc,
+    VoteAverageDesc,
+    VoteCountDesc,
+}
 
-    // Process movies
-    for item in movie_data.results {
-        let poster_url = item
-            .poster_path
-            .map(|path| format!("https://image.tmdb.org/t/p/w500{}", path))
-            .unwrap_or_else(|| String::from("https://via.placeholder.com/500x750"));
+#[derive(Debug, Clone)]
+pub struct DiscoverQuery {
+    media_type: &'static str,
+    sort_by: Option<SortBy>,
+    providers: Vec<u32>,
+    region: Option<String>,
+    monetization_types: Vec<String>,
+    genres: Vec<u32>,
+    without_genres: Vec<u32>,
+    released_after: Option<NaiveDate>,
+    released_before: Option<NaiveDate>,
+    min_vote_count: Option<u32>,
+    min_vote_average: Option<f32>,
+    language: Option<String>,
+    page: u32,
+}
 
-        let tmdb_url = format!("https://www.themoviedb.org/movie/{}", item.id);
+impl DiscoverQuery {
+    fn new(media_type: &'static str) -> Self {
+        Self {
+            media_type,
+            sort_by: None,
+            providers: Vec::new(),
+            region: None,
+            monetization_types: Vec::new(),
+            genres: Vec::new(),
+            without_genres: Vec::new(),
+            released_after: None,
+            released_before: None,
+            min_vote_count: None,
+            min_vote_average: None,
+            language: None,
+            page: 1,
+        }
+    }
 
-        let year = item
-            .release_date
-            .as_ref()
-            .and_then(|date| date.split('-').next())
-            .unwrap_or("");
-
-        let mut imdb_rating = None;
-        let mut metascore = None;
-        let mut rotten_tomatoes = None;
-
-        if let Some(title) = &item.title {
-            if let Ok(omdb_data) = omdb::fetch_ratings(&config, title, year).await {
-                imdb_rating = omdb_data.imdb_rating;
-                metascore = omdb_data.metascore;
-
-                // Extract Rotten Tomatoes rating
-                if let Some(ratings) = omdb_data.ratings {
-                    for rating in ratings {
-                        if rating.source == "Rotten Tomatoes" {
-                            rotten_tomatoes = Some(rating.value.to_string());
-                            break;
-                        }
-                    }
-                }
+    pub fn movie() -> Self {
+        Self::new("movie")
+    }
+
+    pub fn tv() -> Self {
+        Self::new("tv")
+    }
+
+    /// Builds the query for one media type from a discovery profile, relative to `today`.
+    pub fn from_profile(media_type: &str, discovery: &DiscoveryConfig, today: NaiveDate) -> Self {
+        let query = if media_type == "movie" {
+            Self::movie()
+        } else {
+            Self::tv()
+        };
+
+        let query = query
+            .sort_by(SortBy::ReleaseDateDesc)
+            .providers(&discovery.provider_ids())
+            .region(&discovery.region)
+            .monetization_types(if media_type == "movie" {
+                &discovery.movie_monetization_types
+            } else {
+                &discovery.monetization_types
+            })
+            .language(&discovery.language)
+            .min_vote_count(discovery.min_vote_count)
+            .min_vote_average(discovery.min_vote_average);
+
+        match discovery.release_window_days {
+            Some(days) => {
+                query.released_between(today - chrono::Duration::days(i64::from(days)), today)
             }
+            None => query,
         }
+    }
 
-        all_releases.push(Release {
-            id: item.id,
-            title: item.title.unwrap_or_default(),
-            release_date: item.release_date.unwrap_or_default(),
-            media_type: "movie".to_string(),
-            vote_count: item.vote_count,
-            vote_average: item.vote_average,
-            poster_url,
-            tmdb_url,
-            number_of_seasons: None,
-            overview: item.overview,
-            imdb_rating,
-            metascore,
-            rotten_tomatoes,
-        });
-    }
-
-    let tv_data: TMDBResponse = client
-            .tmdb_get(
-                "discover/tv?sort_by=first_air_date.desc&with_watch_providers=8|9|337|1899|350|15|619|283&watch_region=US&with_watch_monetization_types=flatrate&vote_count.gte=1&vote_average.gte=1&page=1",
-                &config.tmdb_api_key,
-            )
-            .await?;
-
-    // Create futures for both TV details and providers
-    let mut tv_futures = Vec::new();
-    for item in &tv_data.results {
-        let config_tv = config.clone();
-        let id = item.id;
-        let tv_future = tokio::spawn(async move {
-            let details = fetch_tv_details(&config_tv, id).await;
-            (id, details)
-        });
-        tv_futures.push((id, item.clone(), tv_future));
-    }
-
-    // Process TV shows
-    for (id, item, future) in tv_futures {
-        match future.await {
-            Ok((_, details_result)) => {
-                let number_of_seasons = match details_result {
-                    Ok(details) => Some(details.number_of_seasons),
-                    Err(e) => {
-                        error!("Failed to fetch TV details for {}: {}", id, e);
-                        None
-                    }
-                };
-
-                let poster_url = item
-                    .poster_path
-                    .map(|path| format!("https://image.tmdb.org/t/p/w500{}", path))
-                    .unwrap_or_else(|| String::from("https://via.placeholder.com/500x750"));
-
-                let tmdb_url = format!("https://www.themoviedb.org/tv/{}", item.id);
-
-                all_releases.push(Release {
-                    id: item.id,
-                    title: item.name.unwrap_or_default(),
-                    release_date: item.first_air_date.unwrap_or_default(),
-                    media_type: "tv".to_string(),
-                    vote_count: item.vote_count,
-                    vote_average: item.vote_average,
-                    poster_url,
-                    tmdb_url,
-                    number_of_seasons,
-                    overview: item.overview,
-                    imdb_rating: None,
-                    metascore: None,
-                    rotten_tomatoes: None,
-                });
-            }
-            Err(e) => {
-                error!("Failed to join TV future: {}", e);
-            }
+    pub fn sort_by(mut self, sort_by: SortBy) -> Self {
+        self.sort_by = Some(sort_by);
+        self
+    }
+
+    pub fn providers(mut self, providers: &[u32]) -> Self {
+        self.providers = providers.to_vec();
+        self
+    }
+
+    pub fn region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+
+    pub fn monetization_types(mut self, types: &[String]) -> Self {
+        self.monetization_types = types.to_vec();
+        self
+    }
+
+    pub fn genres(mut self, genres: &[u32]) -> Self {
+        self.genres = genres.to_vec();
+        self
+    }
+
+    pub fn without_genres(mut self, genres: &[u32]) -> Self {
+        self.without_genres = genres.to_vec();
+        self
+    }
+
+    pub fn released_between(mut self, after: NaiveDate, before: NaiveDate) -> Self {
+        self.released_after = Some(after);
+        self.released_before = Some(before);
+        self
+    }
+
+    pub fn min_vote_count(mut self, count: u32) -> Self {
+        self.min_vote_count = Some(count);
+        self
+    }
+
+    pub fn min_vote_average(mut self, average: f32) -> Self {
+        self.min_vote_average = Some(average);
+        self
+    }
+
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = page;
+        self
+    }
+
+    pub fn media_type(&self) -> &'static str {
+        self.media_type
+    }
+
+    fn sort_param(&self, sort_by: SortBy) -> &'static str {
+        match (sort_by, self.media_type) {
+            (SortBy::PopularityDesc, _) => "popularity.desc",
+            (SortBy::ReleaseDateDesc, "movie") => "primary_release_date.desc",
+            (SortBy::ReleaseDateDesc, _) => "first_air_date.desc",
+            (SortBy::VoteAverageDesc, _) => "vote_average.desc",
+            (SortBy::VoteCountDesc, _) => "vote_count.desc",
+        }
+    }
+
+    fn date_field(&self) -> &'static str {
+        if self.media_type == "movie" {
+            "primary_release_date"
+        } else {
+            "first_air_date"
+        }
+    }
+
+    fn join<T: ToString>(values: &[T], separator: &str) -> String {
+        values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// Renders the endpoint path and URL-encoded query string for `ApiClient::tmdb_get`.
+    pub fn to_endpoint(&self) -> String {
+        let mut params: Vec<(String, String)> = Vec::new();
+
+        if let Some(sort_by) = self.sort_by {
+            params.push(("sort_by".into(), self.sort_param(sort_by).into()));
+        }
+        if !self.providers.is_empty() {
+            params.push((
+                "with_watch_providers".into(),
+                Self::join(&self.providers, "|"),
+            ));
+        }
+        if let Some(region) = &self.region {
+            params.push(("watch_region".into(), region.clone()));
+        }
+        if !self.monetization_types.is_empty() {
+            params.push((
+                "with_watch_monetization_types".into(),
+                self.monetization_types.join("|"),
+            ));
         }
+        if !self.genres.is_empty() {
+            params.push(("with_genres".into(), Self::join(&self.genres, "|")));
+        }
+        if !self.without_genres.is_empty() {
+            params.push((
+                "without_genres".into(),
+                Self::join(&self.without_genres, ","),
+            ));
+        }
+        if let Some(after) = self.released_after {
+            params.push((
+                format!("{}.gte", self.date_field()),
+                after.format("%Y-%m-%d").to_string(),
+            ));
+        }
+        if let Some(before) = self.released_before {
+            params.push((
+                format!("{}.lte", self.date_field()),
+                before.format("%Y-%m-%d").to_string(),
+            ));
+        }
+        if let Some(count) = self.min_vote_count {
+            params.push(("vote_count.gte".into(), count.to_string()));
+        }
+        if let Some(average) = self.min_vote_average {
+            params.push(("vote_average.gte".into(), average.to_string()));
+        }
+        if let Some(language) = &self.language {
+            params.push(("language".into(), language.clone()));
+        }
+        params.push(("page".into(), self.page.to_string()));
+
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        format!("discover/{}?{}", self.media_type, query)
+    }
+
+    /// Renders the same query for Jellyseerr's discover proxy. Jellyseerr has no
+    /// monetization or genre exclusion filters, so those are dropped.
+    pub fn to_jellyseerr_endpoint(&self) -> String {
+        let mut params: Vec<(String, String)> = Vec::new();
+        let date_field = if self.media_type == "movie" {
+            "primaryReleaseDate"
+        } else {
+            "firstAirDate"
+        };
+
+        if let Some(sort_by) = self.sort_by {
+            params.push(("sortBy".into(), self.sort_param(sort_by).into()));
+        }
+        if !self.providers.is_empty() {
+            params.push(("watchProviders".into(), Self::join(&self.providers, "|")));
+        }
+        if let Some(region) = &self.region {
+            params.push(("watchRegion".into(), region.clone()));
+        }
+        if !self.genres.is_empty() {
+            params.push(("genre".into(), Self::join(&self.genres, "|")));
+        }
+        if let Some(after) = self.released_after {
+            params.push((
+                format!("{}Gte", date_field),
+                after.format("%Y-%m-%d").to_string(),
+            ));
+        }
+        if let Some(before) = self.released_before {
+            params.push((
+                format!("{}Lte", date_field),
+                before.format("%Y-%m-%d").to_string(),
+            ));
+        }
+        if let Some(count) = self.min_vote_count {
+            params.push(("voteCountGte".into(), count.to_string()));
+        }
+        if let Some(average) = self.min_vote_average {
+            params.push(("voteAverageGte".into(), average.to_string()));
+        }
+        if let Some(language) = &self.language {
+            params.push(("language".into(), language.clone()));
+        }
+        params.push(("page".into(), self.page.to_string()));
+
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let path = if self.media_type == "movie" {
+            "movies"
+        } else {
+            "tv"
+        };
+        format!("discover/{}?{}", path, query)
     }
+}
+
+/// Season list, watch providers and IMDb ID of a title, whichever source they
+/// were looked up from.
+#[derive(Default)]
+pub struct TitleDetails {
+    pub tv: Option<TVShowDetails>,
+    pub providers: RegionWatchProviders,
+    pub external_ids: ExternalIds,
+}
+
+/// Where the discovery pipeline gets its results from. Paging, enrichment and
+/// sorting are the same for every source; only these lookups differ.
+pub trait DiscoverySource: Sync {
+    /// One page of results for a discover query.
+    fn discover(&self, query: &DiscoverQuery) -> impl Future<Output = Result<TMDBResponse>> + Send;
+
+    fn genre_names(
+        &self,
+        media_type: &str,
+        language: &str,
+    ) -> impl Future<Output = HashMap<i32, String>> + Send;
+
+    /// Lookups that fail are logged and left empty, so one title cannot fail the feed.
+    fn title_details(
+        &self,
+        media_type: &str,
+        id: i32,
+        region: &str,
+    ) -> impl Future<Output = TitleDetails> + Send;
+}
+
+/// Walks the discover endpoint page by page until the source runs out of
+/// results or the profile's `max_pages` cap is reached.
+async fn fetch_discover_results<S: DiscoverySource>(
+    source: &S,
+    query: DiscoverQuery,
+    max_pages: u32,
+) -> Result<Vec<TMDBResult>> {
+    let mut results = Vec::new();
+    let mut seen = HashSet::new();
+    let mut page = 1;
+
+    loop {
+        let data = source.discover(&query.clone().page(page)).await?;
+
+        // Results can shift between pages while we walk them, so drop repeats
+        results.extend(data.results.into_iter().filter(|item| seen.insert(item.id)));
+
+        let last_page = data.total_pages.min(max_pages);
+        if data.page >= last_page {
+            debug!(
+                "Fetched {} {} results across {} of {} pages",
+                results.len(),
+                query.media_type(),
+                data.page,
+                data.total_pages
+            );
+            break;
+        }
+        page = data.page + 1;
+    }
+
+    Ok(results)
+}
+
+/// Runs a discovery profile against a source and enriches every result into
+/// a `Release`, newest first. `enrichment` is shared by every feed, so
+/// refreshes running side by side stay within `enrichment.concurrency` titles.
+pub async fn discover_releases<S: DiscoverySource>(
+    source: &S,
+    config: &AppConfig,
+    discovery: &DiscoveryConfig,
+    enrichment: &Semaphore,
+) -> Result<Vec<Release>> {
+    let today = Utc::now().date_naive();
+
+    let (movie_genres, tv_genres) = tokio::join!(
+        source.genre_names("movie", &discovery.language),
+        source.genre_names("tv", &discovery.language)
+    );
+
+    let (movie_results, tv_results) = tokio::try_join!(
+        fetch_discover_results(
+            source,
+            DiscoverQuery::from_profile("movie", discovery, today),
+            discovery.max_pages,
+        ),
+        fetch_discover_results(
+            source,
+            DiscoverQuery::from_profile("tv", discovery, today),
+            discovery.max_pages,
+        )
+    )?;
+
+    // Every title waits for a permit before it starts its lookups
+    let enrich = |media_type, item, genres| async move {
+        let _permit = enrichment
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+        enrich_release(source, config, media_type, item, &discovery.region, genres).await
+    };
+    let mut all_releases: Vec<Release> = future::join_all(
+        movie_results
+            .into_iter()
+            .map(|item| enrich("movie", item, &movie_genres))
+            .chain(
+                tv_results
+                    .into_iter()
+                    .map(|item| enrich("tv", item, &tv_genres)),
+            ),
+    )
+    .await;
 
     // Sort all releases by release date (newest first)
     all_releases.sort_by(|a, b| b.release_date.cmp(&a.release_date));
@@ -198,20 +654,600 @@ pub async fn fetch_latest_releases(config: &AppConfig) -> Result<Vec<Release>> {
     Ok(all_releases)
 }
 
+/// Turns a discover result into a `Release`, looking up the title's details
+/// and then its OMDB ratings.
+async fn enrich_release<S: DiscoverySource>(
+    source: &S,
+    config: &AppConfig,
+    media_type: &str,
+    item: TMDBResult,
+    region: &str,
+    genre_names: &HashMap<i32, String>,
+) -> Release {
+    let (title, release_date) = if media_type == "movie" {
+        (item.title, item.release_date)
+    } else {
+        (item.name, item.first_air_date)
+    };
+    let year = release_year(release_date.as_deref());
+
+    let details = source.title_details(media_type, item.id, region).await;
+    let imdb_id = details.external_ids.imdb_id;
+    let ratings = external_ratings(
+        config,
+        media_type,
+        imdb_id.as_deref(),
+        title.as_deref(),
+        year,
+    )
+    .await;
+
+    Release {
+        id: item.id,
+        title: title.unwrap_or_default(),
+        release_date: release_date.clone().unwrap_or_default(),
+        media_type: media_type.to_string(),
+        vote_count: item.vote_count,
+        vote_average: item.vote_average,
+        poster_url: poster_url(item.poster_path.as_deref()),
+        tmdb_url: format!("https://www.themoviedb.org/{}/{}", media_type, item.id),
+        number_of_seasons: details.tv.as_ref().map(|tv| tv.number_of_seasons),
+        seasons: details
+            .tv
+            .as_ref()
+            .map(|tv| {
+                let mut seasons: Vec<ReleaseSeason> =
+                    tv.seasons.iter().map(ReleaseSeason::from).collect();
+                seasons.sort_by_key(|season| season.season_number);
+                seasons
+            })
+            .unwrap_or_default(),
+        overview: item.overview,
+        imdb_rating: ratings.as_ref().and_then(|r| r.imdb_rating.clone()),
+        metascore: ratings.as_ref().and_then(|r| r.metascore.clone()),
+        rotten_tomatoes: ratings.as_ref().and_then(OMDBResponse::get_rotten_tomatoes),
+        providers: details.providers.to_release_providers(),
+        genres: map_genres(&item.genre_ids, genre_names),
+        genre_ids: item.genre_ids,
+        imdb_url: imdb_id.as_deref().map(imdb_url),
+        imdb_id,
+        media_status: None,
+    }
+}
+
+/// Discovers straight from the TMDB API.
+struct Tmdb<'a> {
+    config: &'a AppConfig,
+    client: ApiClient,
+}
+
+impl DiscoverySource for Tmdb<'_> {
+    async fn discover(&self, query: &DiscoverQuery) -> Result<TMDBResponse> {
+        self.client
+            .tmdb_get(&query.to_endpoint(), &self.config.tmdb_api_key)
+            .await
+    }
+
+    async fn genre_names(&self, media_type: &str, language: &str) -> HashMap<i32, String> {
+        genre_names(self.config, media_type, language).await
+    }
+
+    async fn title_details(&self, media_type: &str, id: i32, region: &str) -> TitleDetails {
+        let config = self.config;
+        let tv = async {
+            if media_type != "tv" {
+                return None;
+            }
+            match fetch_tv_details(config, id).await {
+                Ok(details) => Some(details),
+                Err(e) => {
+                    error!("Failed to fetch TV details for {}: {}", id, e);
+                    None
+                }
+            }
+        };
+        let providers = async {
+            fetch_watch_providers(config, media_type, id, region)
+                .await
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to fetch watch providers for {}/{}: {}",
+                        media_type, id, e
+                    );
+                    RegionWatchProviders::default()
+                })
+        };
+        let external_ids = async {
+            fetch_external_ids(config, media_type, id)
+                .await
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to fetch external IDs for {}/{}: {}",
+                        media_type, id, e
+                    );
+                    ExternalIds::default()
+                })
+        };
+
+        let (tv, providers, external_ids) = tokio::join!(tv, providers, external_ids);
+        TitleDetails {
+            tv,
+            providers,
+            external_ids,
+        }
+    }
+}
+
+pub async fn fetch_latest_releases(
+    config: &AppConfig,
+    discovery: &DiscoveryConfig,
+    enrichment: &Semaphore,
+) -> Result<Vec<Release>> {
+    let source = Tmdb {
+        config,
+        client: ApiClient::new(config),
+    };
+    discover_releases(&source, config, discovery, enrichment).await
+}
+
 pub async fn fetch_tv_details(config: &AppConfig, tv_id: i32) -> Result<TVShowDetails> {
-    if let Some(cached) = crate::api::cache::get_cached_tv_details(tv_id).await {
+    // Entries cached before season lists were stored only carry a count
+    if let Some(cached) = crate::api::cache::get_cached_tv_details(tv_id)
+        .await
+        .filter(|details| !details.seasons.is_empty() || details.number_of_seasons == 0)
+    {
         debug!("Cache hit for TV details: {}", tv_id);
         return Ok(cached);
     }
 
     debug!("Cache miss for TV show {}, fetching from API", tv_id);
 
-    let client = ApiClient::new(&config);
+    let client = ApiClient::new(config);
     let details: TVShowDetails = client
-        .tmdb_get(&format!("tv/{}", tv_id), &config.tmdb_api_key)
+        .tmdb_get(
+            &format!(
+                "tv/{}?language={}",
+                tv_id,
+                urlencoding::encode(&config.discovery.language)
+            ),
+            &config.tmdb_api_key,
+        )
         .await?;
 
     crate::api::cache::cache_tv_details(tv_id, details.clone()).await;
 
     Ok(details)
 }
+
+pub async fn fetch_watch_providers(
+    config: &AppConfig,
+    media_type: &str,
+    id: i32,
+    region: &str,
+) -> Result<RegionWatchProviders> {
+    if let Some(cached) =
+        crate::api::cache::get_cached_watch_providers(media_type, id, region).await
+    {
+        debug!("Cache hit for watch providers: {}/{}", media_type, id);
+        return Ok(cached);
+    }
+
+    debug!(
+        "Cache miss for watch providers {}/{}, fetching from API",
+        media_type, id
+    );
+
+    let client = ApiClient::new(config);
+    let mut response: WatchProvidersResponse = client
+        .tmdb_get(
+            &format!("{}/{}/watch/providers", media_type, id),
+            &config.tmdb_api_key,
+        )
+        .await?;
+
+    let providers = response.results.remove(region).unwrap_or_default();
+
+    crate::api::cache::cache_watch_providers(media_type, id, region, providers.clone()).await;
+
+    Ok(providers)
+}
+
+pub async fn fetch_external_ids(
+    config: &AppConfig,
+    media_type: &str,
+    id: i32,
+) -> Result<ExternalIds> {
+    if let Some(cached) = crate::api::cache::get_cached_external_ids(media_type, id).await {
+        debug!("Cache hit for external IDs: {}/{}", media_type, id);
+        return Ok(cached);
+    }
+
+    debug!(
+        "Cache miss for external IDs {}/{}, fetching from API",
+        media_type, id
+    );
+
+    let client = ApiClient::new(config);
+    let mut ids: ExternalIds = client
+        .tmdb_get(
+            &format!("{}/{}/external_ids", media_type, id),
+            &config.tmdb_api_key,
+        )
+        .await?;
+
+    // TMDB sometimes returns an empty string rather than null
+    ids.imdb_id = ids.imdb_id.filter(|imdb_id| !imdb_id.is_empty());
+
+    crate::api::cache::cache_external_ids(media_type, id, ids.clone()).await;
+
+    Ok(ids)
+}
+
+/// How OMDB is searched for a title's ratings.
+#[derive(Debug, PartialEq)]
+enum RatingsLookup<'a> {
+    ImdbId(&'a str),
+    Title(&'a str),
+}
+
+/// The IMDb ID pins down the exact title, so a title and year search only
+/// happens without one; it would match remakes and similarly named titles.
+fn ratings_lookup<'a>(
+    imdb_id: Option<&'a str>,
+    title: Option<&'a str>,
+) -> Option<RatingsLookup<'a>> {
+    match imdb_id.filter(|id| !id.is_empty()) {
+        Some(id) => Some(RatingsLookup::ImdbId(id)),
+        None => title
+            .filter(|title| !title.is_empty())
+            .map(RatingsLookup::Title),
+    }
+}
+
+pub async fn external_ratings(
+    config: &AppConfig,
+    media_type: &str,
+    imdb_id: Option<&str>,
+    title: Option<&str>,
+    year: &str,
+) -> Option<OMDBResponse> {
+    match ratings_lookup(imdb_id, title)? {
+        RatingsLookup::ImdbId(id) => {
+            let title = title.filter(|title| !title.is_empty()).unwrap_or(id);
+            match omdb::fetch_ratings(config, media_type, Some(id), title, year).await {
+                Ok(ratings) if ratings.has_ratings() => Some(ratings),
+                Ok(_) => {
+                    debug!("OMDB has no ratings for {} ({})", id, media_type);
+                    None
+                }
+                Err(e) => {
+                    debug!("No OMDB ratings for {} ({}): {}", id, media_type, e);
+                    None
+                }
+            }
+        }
+        RatingsLookup::Title(title) => {
+            match omdb::fetch_ratings(config, media_type, None, title, year).await {
+                Ok(ratings) => Some(ratings),
+                Err(e) => {
+                    debug!("No OMDB ratings for {} ({}): {}", title, media_type, e);
+                    None
+                }
+            }
+        }
+    }
+}
+
+pub fn release_year(date: Option<&str>) -> &str {
+    date.and_then(|date| date.split('-').next()).unwrap_or("")
+}
+
+pub fn imdb_url(imdb_id: &str) -> String {
+    format!("https://www.imdb.com/title/{}/", imdb_id)
+}
+
+pub async fn fetch_genres(
+    config: &AppConfig,
+    media_type: &str,
+    language: &str,
+) -> Result<Vec<Genre>> {
+    if let Some(cached) = crate::api::cache::get_cached_genres(media_type, language).await {
+        debug!("Cache hit for {} genres ({})", media_type, language);
+        return Ok(cached);
+    }
+
+    debug!(
+        "Cache miss for {} genres ({}), fetching from API",
+        media_type, language
+    );
+
+    let client = ApiClient::new(config);
+    let response: GenreListResponse = client
+        .tmdb_get(
+            &format!(
+                "genre/{}/list?language={}",
+                media_type,
+                urlencoding::encode(language)
+            ),
+            &config.tmdb_api_key,
+        )
+        .await?;
+
+    crate::api::cache::cache_genres(media_type, language, response.genres.clone()).await;
+
+    Ok(response.genres)
+}
+
+async fn genre_names(config: &AppConfig, media_type: &str, language: &str) -> HashMap<i32, String> {
+    match fetch_genres(config, media_type, language).await {
+        Ok(genres) => genres
+            .into_iter()
+            .map(|genre| (genre.id, genre.name))
+            .collect(),
+        Err(e) => {
+            error!("Failed to fetch {} genres: {}", media_type, e);
+            HashMap::new()
+        }
+    }
+}
+
+pub fn map_genres(ids: &[i32], names: &HashMap<i32, String>) -> Vec<String> {
+    ids.iter().filter_map(|id| names.get(id).cloned()).collect()
+}
+
+/// Lists every watch provider TMDB knows for a media type in a region.
+pub async fn fetch_provider_list(
+    config: &AppConfig,
+    media_type: &str,
+    region: &str,
+) -> Result<Vec<WatchProviderEntry>> {
+    if let Some(cached) = crate::api::cache::get_cached_provider_list(media_type, region).await {
+        debug!("Cache hit for {} provider list in {}", media_type, region);
+        return Ok(cached);
+    }
+
+    debug!(
+        "Cache miss for {} provider list in {}, fetching from API",
+        media_type, region
+    );
+
+    let client = ApiClient::new(config);
+    let response: WatchProviderListResponse = client
+        .tmdb_get(
+            &format!(
+                "watch/providers/{}?watch_region={}",
+                media_type,
+                urlencoding::encode(region)
+            ),
+            &config.tmdb_api_key,
+        )
+        .await?;
+
+    crate::api::cache::cache_provider_list(media_type, region, response.results.clone()).await;
+
+    Ok(response.results)
+}
+
+pub async fn resolve_discovery_providers(
+    config: &AppConfig,
+    discovery: &mut DiscoveryConfig,
+) -> Result<()> {
+    if discovery
+        .providers
+        .iter()
+        .all(|provider| provider.id().is_some())
+    {
+        return Ok(());
+    }
+
+    let mut known: HashMap<String, (u32, String)> = HashMap::new();
+    for media_type in ["movie", "tv"] {
+        let entries = match discovery.source {
+            ReleaseSource::Tmdb => {
+                fetch_provider_list(config, media_type, &discovery.region).await?
+            }
+            ReleaseSource::Jellyseerr => {
+                jellyseerr::fetch_provider_list(config, media_type, &discovery.region).await?
+            }
+        };
+        for entry in entries {
+            known
+                .entry(entry.provider_name.to_lowercase())
+                .or_insert((entry.provider_id as u32, entry.provider_name));
+        }
+    }
+
+    let mut resolved = Vec::with_capacity(discovery.providers.len());
+    for provider in &discovery.providers {
+        let name = match provider {
+            ProviderRef::Name(name) if provider.id().is_none() => name,
+            _ => {
+                resolved.push(provider.clone());
+                continue;
+            }
+        };
+
+        match known.get(&name.trim().to_lowercase()) {
+            Some((id, _)) => {
+                debug!("Resolved watch provider {} to {}", name, id);
+                resolved.push(ProviderRef::Id(*id));
+            }
+            None => {
+                let mut choices: Vec<&str> = known.values().map(|(_, n)| n.as_str()).collect();
+                choices.sort_unstable();
+                return Err(Error::Config(format!(
+                    "Unknown watch provider \"{}\" for region {}. Valid providers: {}",
+                    name,
+                    discovery.region,
+                    choices.join(", ")
+                )));
+            }
+        }
+    }
+
+    discovery.providers = resolved;
+    Ok(())
+}
+
+/// Replaces provider names in every discovery profile with their TMDB IDs.
+pub async fn resolve_provider_names(config: &mut AppConfig) -> Result<()> {
+    let mut discovery = config.discovery.clone();
+    resolve_discovery_providers(config, &mut discovery).await?;
+
+    let mut feeds = config.feeds.clone();
+    for feed in &mut feeds {
+        resolve_discovery_providers(config, &mut feed.discovery).await?;
+    }
+
+    config.discovery = discovery;
+    config.feeds = feeds;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discover_query_renders_encoded_query_string() {
+        let query = DiscoverQuery::movie()
+            .sort_by(SortBy::ReleaseDateDesc)
+            .providers(&[8, 337])
+            .region("CA")
+            .monetization_types(&["flatrate".to_string(), "ads".to_string()])
+            .genres(&[99])
+            .without_genres(&[10764, 10767])
+            .released_between(
+                NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            )
+            .min_vote_count(10)
+            .min_vote_average(6.5)
+            .language("fr-CA")
+            .page(3);
+
+        assert_eq!(
+            query.to_endpoint(),
+            "discover/movie?sort_by=primary_release_date.desc\
+             &with_watch_providers=8%7C337\
+             &watch_region=CA\
+             &with_watch_monetization_types=flatrate%7Cads\
+             &with_genres=99\
+             &without_genres=10764%2C10767\
+             &primary_release_date.gte=2025-01-01\
+             &primary_release_date.lte=2025-01-31\
+             &vote_count.gte=10\
+             &vote_average.gte=6.5\
+             &language=fr-CA\
+             &page=3"
+        );
+    }
+
+    #[test]
+    fn discover_query_from_profile_uses_tv_date_fields() {
+        let discovery = DiscoveryConfig {
+            source: crate::ReleaseSource::Tmdb,
+            providers: vec![ProviderRef::Id(8)],
+            region: "US".into(),
+            language: "en-US".into(),
+            min_vote_count: 1,
+            min_vote_average: 1.0,
+            monetization_types: vec![],
+            movie_monetization_types: vec![],
+            max_pages: 1,
+            release_window_days: Some(7),
+        };
+        let today = NaiveDate::from_ymd_opt(2025, 3, 8).unwrap();
+
+        assert_eq!(
+            DiscoverQuery::from_profile("tv", &discovery, today).to_endpoint(),
+            "discover/tv?sort_by=first_air_date.desc\
+             &with_watch_providers=8\
+             &watch_region=US\
+             &first_air_date.gte=2025-03-01\
+             &first_air_date.lte=2025-03-08\
+             &vote_count.gte=1\
+             &vote_average.gte=1\
+             &language=en-US\
+             &page=1"
+        );
+    }
+
+    #[test]
+    fn movies_are_only_filtered_by_monetization_when_asked() {
+        let mut discovery = DiscoveryConfig::default();
+        let today = NaiveDate::from_ymd_opt(2025, 3, 8).unwrap();
+        let filter = "with_watch_monetization_types=flatrate";
+
+        assert!(DiscoverQuery::from_profile("tv", &discovery, today)
+            .to_endpoint()
+            .contains(filter));
+        assert!(!DiscoverQuery::from_profile("movie", &discovery, today)
+            .to_endpoint()
+            .contains("with_watch_monetization_types"));
+
+        discovery.movie_monetization_types = vec!["flatrate".into()];
+        assert!(DiscoverQuery::from_profile("movie", &discovery, today)
+            .to_endpoint()
+            .contains(filter));
+    }
+
+    #[test]
+    fn feeds_can_turn_the_release_window_off() {
+        let base = DiscoveryConfig::default();
+        let overrides = |days| crate::config::settings::DiscoveryOverrides {
+            release_window_days: days,
+            ..Default::default()
+        };
+
+        assert_eq!(overrides(None).apply(&base).release_window_days, Some(30));
+        assert_eq!(overrides(Some(7)).apply(&base).release_window_days, Some(7));
+        assert_eq!(overrides(Some(0)).apply(&base).release_window_days, None);
+    }
+
+    #[test]
+    fn discover_query_renders_jellyseerr_endpoint() {
+        let query = DiscoverQuery::tv()
+            .sort_by(SortBy::ReleaseDateDesc)
+            .providers(&[8, 337])
+            .region("CA")
+            .monetization_types(&["flatrate".to_string()])
+            .without_genres(&[10764])
+            .released_between(
+                NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            )
+            .min_vote_count(10)
+            .language("en-US")
+            .page(2);
+
+        assert_eq!(
+            query.to_jellyseerr_endpoint(),
+            "discover/tv?sortBy=first_air_date.desc\
+             &watchProviders=8%7C337\
+             &watchRegion=CA\
+             &firstAirDateGte=2025-01-01\
+             &firstAirDateLte=2025-01-31\
+             &voteCountGte=10\
+             &language=en-US\
+             &page=2"
+        );
+    }
+
+    #[test]
+    fn ratings_are_only_searched_by_title_without_an_imdb_id() {
+        assert_eq!(
+            ratings_lookup(Some("tt0111161"), Some("Dune")),
+            Some(RatingsLookup::ImdbId("tt0111161"))
+        );
+        assert_eq!(
+            ratings_lookup(Some("tt0111161"), None),
+            Some(RatingsLookup::ImdbId("tt0111161"))
+        );
+        assert_eq!(
+            ratings_lookup(Some(""), Some("Dune")),
+            Some(RatingsLookup::Title("Dune"))
+        );
+        assert_eq!(ratings_lookup(None, Some("")), None);
+    }
+}
@@ -1,29 +1,283 @@
-use config::{Config, ConfigError, Environment, File};
+use config::builder::DefaultState;
+use config::{Config, ConfigBuilder, ConfigError, Environment, File, Map};
 use secrecy::{ExposeSecret, Secret};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 use tracing::warn;
 
-use crate::security::deserialize::deserialize_secret_string;
+use crate::api::jellyseerr::MediaStatus;
+use crate::security::auth::Role;
+use crate::security::deserialize::{
+    deserialize_networks, deserialize_secret_string, read_secret_file,
+};
+use ipnet::IpNet;
 
 #[derive(Debug, Deserialize)]
 pub struct TMDBConfig {
-    #[serde(deserialize_with = "deserialize_secret_string")]
+    // Only needed when releases come from TMDB
+    #[serde(
+        deserialize_with = "deserialize_secret_string",
+        default = "empty_secret"
+    )]
     pub api_key: Secret<String>,
     pub refresh_interval: u64,
 }
 
+fn empty_secret() -> Secret<String> {
+    Secret::new(String::new())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct JellyseerrConfig {
     #[serde(deserialize_with = "deserialize_secret_string")]
     pub api_key: Secret<String>,
     pub url: String,
+    /// Titles and seasons with one of these statuses are left out of the feeds.
+    #[serde(default = "default_hidden_statuses")]
+    pub hidden_statuses: Vec<MediaStatus>,
+    /// Jellyseerr user that requests are attributed to when the requester has
+    /// no Jellyseerr account of their own. Without it the API key's owner is used.
+    pub default_user_id: Option<i32>,
+    /// App usernames mapped to Jellyseerr user IDs, for names Jellyseerr
+    /// does not know under the same username or email.
+    #[serde(default)]
+    pub users: HashMap<String, i32>,
+}
+
+fn default_hidden_statuses() -> Vec<MediaStatus> {
+    MediaStatus::ALL.to_vec()
+}
+
+/// How visitors prove who they are before using the app.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    /// Anyone who can reach the app may use it.
+    None,
+    /// Sign in with a Jellyseerr local account or Jellyfin credentials.
+    #[default]
+    Jellyseerr,
+    /// Sign in with accounts kept by tmdb2seer itself, see `users_file`.
+    Local,
+    /// Trust the user a reverse proxy such as Authelia or Authentik signed in,
+    /// see `[auth.proxy]`.
+    Proxy,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub mode: AuthMode,
+    /// Key session cookies are signed with. A random one is generated when
+    /// empty, which signs everyone out on restart.
+    #[serde(deserialize_with = "deserialize_secret_string")]
+    pub session_secret: Secret<String>,
+    pub session_ttl_hours: u64,
+    /// Only send the session cookie over HTTPS. Always on when the app serves
+    /// TLS itself.
+    pub secure_cookie: bool,
+    /// Where `local` accounts are stored.
+    pub users_file: String,
+    /// Where sessions signed out before they expired are remembered.
+    pub revoked_sessions_file: String,
+    pub proxy: ProxyAuthConfig,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            mode: AuthMode::default(),
+            session_secret: empty_secret(),
+            session_ttl_hours: 24 * 7,
+            secure_cookie: false,
+            users_file: "data/users.json".into(),
+            revoked_sessions_file: "data/revoked_sessions.json".into(),
+            proxy: ProxyAuthConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ProxyAuthConfig {
+    /// Networks the reverse proxy connects from. Requests from anywhere else
+    /// are refused, so the user header cannot be forged.
+    #[serde(deserialize_with = "deserialize_networks")]
+    pub trusted_proxies: Vec<IpNet>,
+    pub user_header: String,
+    pub email_header: String,
+    /// Role of every user the proxy signs in.
+    pub role: Role,
+}
+
+impl Default for ProxyAuthConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: Vec::new(),
+            user_header: "Remote-User".into(),
+            email_header: "Remote-Email".into(),
+            role: Role::Requester,
+        }
+    }
+}
+
+/// Response headers sent with every page and API call.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SecurityConfig {
+    pub hsts: HstsConfig,
+    pub frame_options: FrameOptions,
+    pub referrer_policy: String,
+    /// Sent with pages and API responses, static files are left to the browser.
+    pub cache_control: String,
+    pub cors: CorsConfig,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            hsts: HstsConfig::default(),
+            frame_options: FrameOptions::default(),
+            referrer_policy: "strict-origin-when-cross-origin".into(),
+            cache_control: "no-store, max-age=0".into(),
+            cors: CorsConfig::default(),
+        }
+    }
+}
+
+const REFERRER_POLICIES: [&str; 8] = [
+    "no-referrer",
+    "no-referrer-when-downgrade",
+    "origin",
+    "origin-when-cross-origin",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+    "unsafe-url",
+];
+
+impl SecurityConfig {
+    fn validate(&self) -> Result<(), String> {
+        if !REFERRER_POLICIES.contains(&self.referrer_policy.as_str()) {
+            return Err(format!(
+                "Unknown referrer_policy {:?}, expected one of: {}",
+                self.referrer_policy,
+                REFERRER_POLICIES.join(", ")
+            ));
+        }
+        if http::HeaderValue::from_str(&self.cache_control).is_err() {
+            return Err(format!("Invalid cache_control {:?}", self.cache_control));
+        }
+        // https://hstspreload.org lists what preloading requires
+        if self.hsts.preload && (self.hsts.max_age < 31_536_000 || !self.hsts.include_subdomains) {
+            return Err(
+                "HSTS preload needs max_age of at least 31536000 and include_subdomains".into(),
+            );
+        }
+        for origin in &self.cors.allowed_origins {
+            if origin == "*" {
+                return Err("CORS allowed_origins must list origins, not \"*\"".into());
+            }
+            if http::HeaderValue::from_str(origin).is_err() || origin.ends_with('/') {
+                return Err(format!(
+                    "Invalid CORS origin {:?}, expected e.g. \"https://dashboard.example.com\"",
+                    origin
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `Strict-Transport-Security`. Only enable it once the app is served over HTTPS.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HstsConfig {
+    pub enabled: bool,
+    pub max_age: u64,
+    pub include_subdomains: bool,
+    pub preload: bool,
+}
+
+impl Default for HstsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age: 31_536_000,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+}
+
+/// Who may show the app in a frame, sent as `X-Frame-Options` and the CSP
+/// `frame-ancestors` directive.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameOptions {
+    Deny,
+    #[default]
+    SameOrigin,
+}
+
+/// Cross-origin read access to `/api`, for other dashboards that call it from
+/// the browser. Only GET is allowed; state-changing calls need the app's own
+/// CSRF token, which other origins cannot get.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Origins such as `https://dashboard.example.com`. Empty allows none.
+    pub allowed_origins: Vec<String>,
+    /// Let those origins send the session cookie along.
+    pub allow_credentials: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// PEM certificate chain. Together with `tls_key` the app serves HTTPS itself.
+    #[serde(default)]
+    pub tls_cert: Option<String>,
+    /// PEM private key for `tls_cert`.
+    #[serde(default)]
+    pub tls_key: Option<String>,
+    /// Seconds between checks for a renewed certificate.
+    #[serde(default = "default_tls_reload_interval")]
+    pub tls_reload_interval: u64,
+    /// Port of a plain HTTP listener that redirects everything to HTTPS.
+    #[serde(default)]
+    pub http_redirect_port: Option<u16>,
+}
+
+fn default_tls_reload_interval() -> u64 {
+    300
+}
+
+impl ServerConfig {
+    /// Certificate and key paths, when TLS is enabled.
+    pub fn tls(&self) -> Option<(&str, &str)> {
+        self.tls_cert.as_deref().zip(self.tls_key.as_deref())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.tls_cert.is_some() != self.tls_key.is_some() {
+            return Err("TLS needs both server.tls_cert and server.tls_key".into());
+        }
+        if self.tls_reload_interval == 0 {
+            return Err("Server tls_reload_interval must be positive".into());
+        }
+        match self.http_redirect_port {
+            Some(_) if self.tls().is_none() => {
+                Err("Server http_redirect_port needs TLS to redirect to".into())
+            }
+            Some(port) if port == self.port => {
+                Err("Server http_redirect_port must differ from port".into())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -55,6 +309,309 @@ impl Default for RetryConfig {
     }
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct EnrichmentConfig {
+    /// Titles enriched at once across all feeds; each issues its TMDB and OMDB
+    /// lookups in parallel.
+    pub concurrency: usize,
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        Self { concurrency: 8 }
+    }
+}
+
+/// A watch provider given either by its TMDB ID or by its display name.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ProviderRef {
+    Id(u32),
+    Name(String),
+}
+
+impl ProviderRef {
+    /// Returns the numeric ID, treating numeric strings (e.g. from env vars) as IDs.
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            ProviderRef::Id(id) => Some(*id),
+            ProviderRef::Name(name) => name.trim().parse().ok(),
+        }
+    }
+}
+
+/// Where release lists come from. Jellyseerr proxies TMDB discovery, so it
+/// works without a TMDB key and already knows each title's media status.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseSource {
+    #[default]
+    Tmdb,
+    Jellyseerr,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DiscoveryConfig {
+    /// Applies to every feed; feeds cannot override it.
+    #[serde(default)]
+    pub source: ReleaseSource,
+    pub providers: Vec<ProviderRef>,
+    pub region: String,
+    pub language: String,
+    pub min_vote_count: u32,
+    pub min_vote_average: f32,
+    /// How TV shows must be watchable, e.g. only on subscription.
+    pub monetization_types: Vec<String>,
+    /// The same for movies. Empty by default, since new movies are often rent
+    /// or buy only before they reach a subscription.
+    #[serde(default)]
+    pub movie_monetization_types: Vec<String>,
+    pub max_pages: u32,
+    pub release_window_days: Option<u32>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            source: ReleaseSource::default(),
+            providers: [8, 9, 337, 1899, 350, 15, 619, 283]
+                .into_iter()
+                .map(ProviderRef::Id)
+                .collect(),
+            region: "US".into(),
+            language: "en-US".into(),
+            min_vote_count: 1,
+            min_vote_average: 1.0,
+            monetization_types: vec!["flatrate".into()],
+            movie_monetization_types: Vec::new(),
+            max_pages: 5,
+            release_window_days: Some(30),
+        }
+    }
+}
+
+const MONETIZATION_TYPES: &[&str] = &["flatrate", "free", "ads", "rent", "buy"];
+
+// TMDB refuses discover pages beyond 500
+const TMDB_MAX_PAGES: u32 = 500;
+
+impl DiscoveryConfig {
+    /// Provider IDs for the discover query. Names must already have been resolved.
+    pub fn provider_ids(&self) -> Vec<u32> {
+        self.providers.iter().filter_map(ProviderRef::id).collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.providers.is_empty() {
+            return Err("At least one discovery provider is required".into());
+        }
+        if self.region.len() != 2 || !self.region.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!(
+                "Discovery region must be an ISO 3166-1 code such as \"US\", got \"{}\"",
+                self.region
+            ));
+        }
+        if self.language.is_empty() {
+            return Err("Discovery language is required".into());
+        }
+        if !(0.0..=10.0).contains(&self.min_vote_average) {
+            return Err(format!(
+                "Discovery min_vote_average must be between 0 and 10, got {}",
+                self.min_vote_average
+            ));
+        }
+        if let Some(kind) = self
+            .monetization_types
+            .iter()
+            .chain(&self.movie_monetization_types)
+            .find(|kind| !MONETIZATION_TYPES.contains(&kind.as_str()))
+        {
+            return Err(format!(
+                "Unknown monetization type \"{}\", expected one of: {}",
+                kind,
+                MONETIZATION_TYPES.join(", ")
+            ));
+        }
+        if !(1..=TMDB_MAX_PAGES).contains(&self.max_pages) {
+            return Err(format!(
+                "Discovery max_pages must be between 1 and {}, got {}",
+                TMDB_MAX_PAGES, self.max_pages
+            ));
+        }
+        if self.release_window_days == Some(0) {
+            return Err("Discovery release_window_days must be positive".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct DiscoveryOverrides {
+    pub providers: Option<Vec<ProviderRef>>,
+    pub region: Option<String>,
+    pub language: Option<String>,
+    pub min_vote_count: Option<u32>,
+    pub min_vote_average: Option<f32>,
+    pub monetization_types: Option<Vec<String>>,
+    pub movie_monetization_types: Option<Vec<String>>,
+    pub max_pages: Option<u32>,
+    /// `0` turns the default window off for this feed.
+    pub release_window_days: Option<u32>,
+}
+
+impl DiscoveryOverrides {
+    /// Layers `update` on top, keeping the keys it leaves out.
+    pub fn merge(&self, update: &DiscoveryOverrides) -> DiscoveryOverrides {
+        DiscoveryOverrides {
+            providers: update.providers.clone().or_else(|| self.providers.clone()),
+            region: update.region.clone().or_else(|| self.region.clone()),
+            language: update.language.clone().or_else(|| self.language.clone()),
+            min_vote_count: update.min_vote_count.or(self.min_vote_count),
+            min_vote_average: update.min_vote_average.or(self.min_vote_average),
+            monetization_types: update
+                .monetization_types
+                .clone()
+                .or_else(|| self.monetization_types.clone()),
+            movie_monetization_types: update
+                .movie_monetization_types
+                .clone()
+                .or_else(|| self.movie_monetization_types.clone()),
+            max_pages: update.max_pages.or(self.max_pages),
+            release_window_days: update.release_window_days.or(self.release_window_days),
+        }
+    }
+
+    pub fn apply(&self, base: &DiscoveryConfig) -> DiscoveryConfig {
+        DiscoveryConfig {
+            source: base.source,
+            providers: self
+                .providers
+                .clone()
+                .unwrap_or_else(|| base.providers.clone()),
+            region: self.region.clone().unwrap_or_else(|| base.region.clone()),
+            language: self
+                .language
+                .clone()
+                .unwrap_or_else(|| base.language.clone()),
+            min_vote_count: self.min_vote_count.unwrap_or(base.min_vote_count),
+            min_vote_average: self.min_vote_average.unwrap_or(base.min_vote_average),
+            monetization_types: self
+                .monetization_types
+                .clone()
+                .unwrap_or_else(|| base.monetization_types.clone()),
+            movie_monetization_types: self
+                .movie_monetization_types
+                .clone()
+                .unwrap_or_else(|| base.movie_monetization_types.clone()),
+            max_pages: self.max_pages.unwrap_or(base.max_pages),
+            release_window_days: match self.release_window_days {
+                Some(0) => None,
+                Some(days) => Some(days),
+                None => base.release_window_days,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeedSettings {
+    pub id: String,
+    pub name: String,
+    pub refresh_interval: Option<u64>,
+    #[serde(default)]
+    pub discovery: DiscoveryOverrides,
+}
+
+/// A feed with its discovery profile resolved against the `[discovery]` defaults.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedConfig {
+    pub id: String,
+    pub name: String,
+    pub refresh_interval: u64,
+    pub discovery: DiscoveryConfig,
+    /// What the feed sets itself, so changed defaults reach the other keys.
+    pub overrides: DiscoveryOverrides,
+}
+
+pub const DEFAULT_FEED_ID: &str = "latest";
+
+/// Secrets that can also be read from the file an `APP_<KEY>_FILE` variable
+/// names, as with Docker secrets.
+const SECRET_KEYS: [&str; 4] = [
+    "tmdb.api_key",
+    "jellyseerr.api_key",
+    "omdb.api_key",
+    "auth.session_secret",
+];
+
+/// Settings that take a comma-separated list from their `APP_*` variable.
+const LIST_KEYS: [&str; 6] = [
+    "discovery.providers",
+    "discovery.monetization_types",
+    "discovery.movie_monetization_types",
+    "jellyseerr.hidden_statuses",
+    "auth.proxy.trusted_proxies",
+    "security.cors.allowed_origins",
+];
+
+fn env_var(key: &str) -> String {
+    format!("APP_{}", key.replace('.', "__").to_uppercase())
+}
+
+/// Adds the `APP_*` variables on top of the config files. Values stay strings
+/// rather than being guessed at, so `08123456` keeps its leading zero; list
+/// settings are split here instead.
+fn add_environment(
+    builder: ConfigBuilder<DefaultState>,
+    vars: Map<String, String>,
+) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+    let lists: Vec<(&str, Vec<String>)> = LIST_KEYS
+        .iter()
+        .filter_map(|key| {
+            let value = vars.get(&env_var(key))?;
+            let items = value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect();
+            Some((*key, items))
+        })
+        .collect();
+
+    let mut builder = builder.add_source(
+        Environment::with_prefix("APP")
+            .prefix_separator("_")
+            .separator("__")
+            .source(Some(vars)),
+    );
+    for (key, items) in lists {
+        builder = builder.set_override(key, items)?;
+    }
+    Ok(builder)
+}
+
+fn secret_file_overrides() -> Result<Vec<(&'static str, String)>, ConfigError> {
+    SECRET_KEYS
+        .iter()
+        .filter_map(|key| {
+            let var = env_var(key);
+            let path = env::var(format!("{}_FILE", var)).ok()?;
+            if env::var_os(&var).is_some() {
+                return Some(Err(ConfigError::Message(format!(
+                    "Set either {} or {}_FILE, not both",
+                    var, var
+                ))));
+            }
+            Some(
+                read_secret_file(&path)
+                    .map(|secret| (*key, secret))
+                    .map_err(|e| ConfigError::Message(format!("{}_FILE: {}", var, e))),
+            )
+        })
+        .collect()
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub tmdb: TMDBConfig,
@@ -63,6 +620,15 @@ pub struct Settings {
     pub rate_limit: RateLimitConfig,
     pub omdb: OMDBConfig,
     pub retry: RetryConfig,
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub feeds: Vec<FeedSettings>,
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
 }
 
 impl Settings {
@@ -72,26 +638,138 @@ impl Settings {
             "development".into()
         });
 
-        let final_config = Config::builder()
+        let builder = Config::builder()
             .add_source(File::with_name("config/default"))
-            .add_source(File::with_name(&format!("config/{}", run_mode)))
-            .add_source(Environment::with_prefix("APP").separator("__"))
-            .build()?;
+            .add_source(File::with_name(&format!("config/{}", run_mode)));
+        let mut builder = add_environment(builder, env::vars().collect())?;
+        for (key, secret) in secret_file_overrides()? {
+            builder = builder.set_override(key, secret)?;
+        }
 
-        final_config.try_deserialize()
+        builder.build()?.try_deserialize()
     }
 
     pub fn validate(self) -> Result<Self, String> {
-        if self.tmdb.api_key.expose_secret().is_empty() {
+        if self.discovery.source == ReleaseSource::Tmdb
+            && self.tmdb.api_key.expose_secret().is_empty()
+        {
             return Err("TMDB API key is required".into());
         }
         if self.jellyseerr.api_key.expose_secret().is_empty() {
             return Err("Jellyseerr API key is required".into());
         }
+        self.discovery.validate()?;
+        self.validate_feeds()?;
+        if self.enrichment.concurrency == 0 {
+            return Err("Enrichment concurrency must be at least 1".into());
+        }
+        if self.auth.session_ttl_hours == 0 {
+            return Err("Auth session_ttl_hours must be positive".into());
+        }
+        if self.auth.mode == AuthMode::Proxy && self.auth.proxy.trusted_proxies.is_empty() {
+            return Err("Proxy auth needs at least one trusted_proxies network".into());
+        }
+        self.security.validate()?;
+        self.server.validate()?;
         Ok(self)
     }
 
+    fn validate_feeds(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        for feed in self.feeds() {
+            if feed.id.is_empty()
+                || !feed
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Feed id \"{}\" must be non-empty and contain only a-z, 0-9, '-' or '_'",
+                    feed.id
+                ));
+            }
+            if !seen.insert(feed.id.clone()) {
+                return Err(format!("Duplicate feed id \"{}\"", feed.id));
+            }
+            if feed.refresh_interval == 0 {
+                return Err(format!(
+                    "Feed \"{}\" refresh_interval must be positive",
+                    feed.id
+                ));
+            }
+            feed.discovery
+                .validate()
+                .map_err(|e| format!("Feed \"{}\": {}", feed.id, e))?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the configured feeds, falling back to a single feed built from
+    /// `[discovery]` when none are defined.
+    pub fn feeds(&self) -> Vec<FeedConfig> {
+        if self.feeds.is_empty() {
+            return vec![FeedConfig {
+                id: DEFAULT_FEED_ID.into(),
+                name: "Latest Releases".into(),
+                refresh_interval: self.tmdb.refresh_interval,
+                discovery: self.discovery.clone(),
+                overrides: DiscoveryOverrides::default(),
+            }];
+        }
+
+        self.feeds
+            .iter()
+            .map(|feed| FeedConfig {
+                id: feed.id.clone(),
+                name: feed.name.clone(),
+                refresh_interval: feed.refresh_interval.unwrap_or(self.tmdb.refresh_interval),
+                discovery: feed.discovery.apply(&self.discovery),
+                overrides: feed.discovery.clone(),
+            })
+            .collect()
+    }
+
     pub fn is_development(&self) -> bool {
         env::var("RUN_MODE").unwrap_or_else(|_| "development".into()) == "development"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_env(vars: &[(&str, &str)]) -> Config {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        add_environment(Config::builder(), vars)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn splits_list_variables() {
+        let config = from_env(&[
+            ("APP_DISCOVERY__PROVIDERS", "8"),
+            ("APP_DISCOVERY__MONETIZATION_TYPES", "flatrate, free"),
+        ]);
+
+        let providers: Vec<ProviderRef> = config.get("discovery.providers").unwrap();
+        assert_eq!(
+            providers.iter().map(ProviderRef::id).collect::<Vec<_>>(),
+            [Some(8)]
+        );
+        let types: Vec<String> = config.get("discovery.monetization_types").unwrap();
+        assert_eq!(types, ["flatrate", "free"]);
+    }
+
+    #[test]
+    fn keeps_numeric_secrets_as_written() {
+        let config = from_env(&[("APP_OMDB__API_KEY", "08123456")]);
+
+        let omdb: OMDBConfig = config.get("omdb").unwrap();
+        assert_eq!(omdb.api_key.expose_secret(), "08123456");
+    }
+}
@@ -1,10 +1,20 @@
-use crate::config::settings::Settings;
+use crate::config::settings::{ServerConfig, Settings};
+use crate::security::auth::Role;
+use crate::security::users::UserStore;
 use crate::Error;
-use crate::{api, AppState, Result};
+use crate::{api, AppConfig, AppState, AuthMode, Result};
+use axum::response::{IntoResponse, Redirect};
 use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use futures::future::BoxFuture;
+use http::header::HOST;
+use http::uri::Authority;
+use http::{HeaderMap, StatusCode, Uri};
+use std::future::IntoFuture;
 use std::net::{IpAddr, SocketAddr};
-use std::time::Duration;
-use tracing::{debug, error, Level};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, error, info, warn, Level};
 
 pub fn load_settings() -> Result<Settings> {
     Settings::new()
@@ -25,16 +35,61 @@ pub fn init_logging(settings: &Settings) {
     tracing_subscriber::fmt().with_max_level(level).init();
 }
 
-pub async fn setup_background_tasks(state: &AppState, settings: &Settings) -> Result<()> {
-    let background_state = state.clone();
-    let refresh_interval = Duration::from_secs(settings.tmdb.refresh_interval);
+pub async fn resolve_watch_providers(config: &mut AppConfig) -> Result<()> {
+    api::tmdb::resolve_provider_names(config)
+        .await
+        .map_err(|e| {
+            error!("Failed to resolve watch providers: {}", e);
+            e
+        })
+}
 
-    tokio::spawn(async move {
-        api::tasks::refresh_releases(background_state, refresh_interval).await;
-    });
+/// Opens the native account store, which only exists in `local` auth mode.
+pub async fn load_user_store(config: &AppConfig) -> Result<UserStore> {
+    if config.auth.mode != AuthMode::Local {
+        return Ok(UserStore::empty());
+    }
+
+    let users = UserStore::open(&config.auth.users_file).await?;
+    if users.is_empty().await {
+        warn!(
+            "No accounts in {}, create one with `tmdb2seer create-admin <username>`",
+            config.auth.users_file
+        );
+    }
+    Ok(users)
+}
+
+/// Runs a command given on the command line instead of starting the server.
+pub async fn run_command(config: &AppConfig, args: &[String]) -> Result<()> {
+    match args {
+        [command, username] if command == "create-admin" => {
+            let users = UserStore::open(&config.auth.users_file).await?;
+            let password = match std::env::var("TMDB2SEER_PASSWORD") {
+                Ok(password) => password,
+                Err(_) => rpassword::prompt_password(format!("Password for {}: ", username))?,
+            };
+            users.create(username, &password, Role::Admin).await?;
+            info!("Created admin {} in {}", username, config.auth.users_file);
+            Ok(())
+        }
+        _ => Err(Error::Config(
+            "Usage: tmdb2seer [create-admin <username>]".into(),
+        )),
+    }
+}
 
-    if let Err(e) = api::tmdb::fetch_latest_releases(&state.config).await {
-        error!("Failed initial fetch of latest releases: {}", e);
+/// Starts a refresh task per feed. Their first tick is immediate, so every
+/// feed is fetched right away, side by side, without holding up startup.
+pub async fn setup_background_tasks(state: &AppState) -> Result<()> {
+    for feed in &state.config.feeds {
+        let background_state = state.clone();
+        let feed_id = feed.id.clone();
+        let refresh_interval = feed.refresh_interval;
+
+        tokio::spawn(async move {
+            api::tasks::refresh_releases(background_state, feed_id, refresh_interval).await;
+        });
     }
 
     Ok(())
@@ -49,11 +104,32 @@ pub fn get_socket_addr(settings: &Settings) -> Result<SocketAddr> {
     Ok(SocketAddr::new(ip_addr, settings.server.port))
 }
 
-pub async fn setup_server(app: Router, addr: SocketAddr) -> Result<()> {
-    let server = axum::serve(
-        tokio::net::TcpListener::bind(addr).await?,
-        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-    );
+pub async fn setup_server(app: Router, addr: SocketAddr, server: &ServerConfig) -> Result<()> {
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    let server: BoxFuture<'static, std::io::Result<()>> = match server.tls() {
+        None => {
+            Box::pin(axum::serve(tokio::net::TcpListener::bind(addr).await?, service).into_future())
+        }
+        Some((cert, key)) => {
+            let tls = load_certificates(cert, key).await?;
+            tokio::spawn(reload_certificates(
+                tls.clone(),
+                PathBuf::from(cert),
+                PathBuf::from(key),
+                Duration::from_secs(server.tls_reload_interval),
+            ));
+
+            if let Some(port) = server.http_redirect_port {
+                let listener =
+                    tokio::net::TcpListener::bind(SocketAddr::new(addr.ip(), port)).await?;
+                info!("Redirecting http://{}:{} to HTTPS", addr.ip(), port);
+                tokio::spawn(redirect_to_https(listener, addr.port()));
+            }
+
+            Box::pin(axum_server::bind_rustls(addr, tls).serve(service))
+        }
+    };
 
     tokio::select! {
         _ = tokio::signal::ctrl_c() => {
@@ -68,3 +144,94 @@ pub async fn setup_server(app: Router, addr: SocketAddr) -> Result<()> {
         }
     }
 }
+
+async fn load_certificates(cert: &str, key: &str) -> Result<RustlsConfig> {
+    // Fails when a provider is already installed, which is just as good
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    RustlsConfig::from_pem_file(cert, key).await.map_err(|e| {
+        error!("Failed to load TLS certificate: {}", e);
+        Error::Config(format!(
+            "Cannot load TLS certificate {} and key {}: {}",
+            cert, key, e
+        ))
+    })
+}
+
+async fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let cert = tokio::fs::metadata(cert).await.ok()?.modified().ok()?;
+    let key = tokio::fs::metadata(key).await.ok()?.modified().ok()?;
+    Some((cert, key))
+}
+
+/// Swaps in renewed certificates without a restart, e.g. after certbot ran.
+async fn reload_certificates(tls: RustlsConfig, cert: PathBuf, key: PathBuf, every: Duration) {
+    let mut loaded = modified(&cert, &key).await;
+    let mut interval = tokio::time::interval(every);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let current = modified(&cert, &key).await;
+        if current.is_none() || current == loaded {
+            continue;
+        }
+
+        match tls.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => {
+                info!("Reloaded TLS certificate {}", cert.display());
+                loaded = current;
+            }
+            // A renewal may have written only one of the files so far
+            Err(e) => warn!("Failed to reload TLS certificate: {}", e),
+        }
+    }
+}
+
+/// The HTTPS address of a plain HTTP request, on the same host.
+fn https_url(host: &str, https_port: u16, uri: &Uri) -> Option<String> {
+    let authority: Authority = host.parse().ok()?;
+    let host = authority.host();
+    let port = if https_port == 443 {
+        String::new()
+    } else {
+        format!(":{}", https_port)
+    };
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+
+    Some(format!("https://{}{}{}", host, port, path))
+}
+
+async fn redirect_to_https(listener: tokio::net::TcpListener, https_port: u16) {
+    let app = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        headers
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .and_then(|host| https_url(host, https_port, &uri))
+            .map(|url| Redirect::permanent(&url).into_response())
+            .unwrap_or_else(|| StatusCode::BAD_REQUEST.into_response())
+    });
+
+    if let Err(e) = axum::serve(listener, app).await {
+        error!("HTTPS redirect listener failed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirects_to_the_https_port_of_the_same_host() {
+        let uri: Uri = "/feeds/netflix?exclude_genres=Reality".parse().unwrap();
+        assert_eq!(
+            https_url("media.example.com:8080", 443, &uri).as_deref(),
+            Some("https://media.example.com/feeds/netflix?exclude_genres=Reality")
+        );
+        assert_eq!(
+            https_url("[::1]:80", 3443, &"/".parse().unwrap()).as_deref(),
+            Some("https://[::1]:3443/")
+        );
+        assert!(https_url("bad host/", 443, &uri).is_none());
+    }
+}
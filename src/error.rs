@@ -29,6 +29,18 @@ pub enum Error {
     #[error("Request error: {0}")]
     Request(String),
 
+    #[error("Invalid request: {0}")]
+    BadRequest(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("Parse error: {0}")]
     Parse(String),
 
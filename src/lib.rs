@@ -1,4 +1,5 @@
 pub mod api {
+    pub mod admin;
     pub mod cache;
     pub mod client;
     pub mod handlers;
@@ -14,25 +15,38 @@ pub mod config {
     pub mod setup;
 }
 pub mod security {
+    pub mod auth;
     pub mod csrf;
     pub mod deserialize;
     pub mod headers;
+    pub mod proxy;
+    pub mod session;
+    pub mod users;
 }
 pub mod utils {
     pub mod serde;
 }
 
 mod error;
-pub use config::settings::RetryConfig;
+#[cfg(test)]
+mod testing;
+pub use config::settings::{
+    AuthConfig, AuthMode, DiscoveryConfig, DiscoveryOverrides, EnrichmentConfig, FeedConfig,
+    ProviderRef, ProxyAuthConfig, ReleaseSource, RetryConfig, SecurityConfig,
+};
 pub use error::{Error, Result};
 
+pub use api::jellyseerr::MediaStatus;
 pub use api::tmdb::Release;
 use chrono::{DateTime, Utc};
 pub use config::settings::Settings;
 use secrecy::Secret;
-use serde_json;
+use security::csrf::CsrfTokens;
+use security::session::SessionSigner;
+use security::users::UserStore;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 
@@ -41,9 +55,17 @@ pub struct AppConfig {
     pub tmdb_api_key: Secret<String>,
     pub jellyseerr_api_key: Secret<String>,
     pub jellyseerr_url: String,
+    pub jellyseerr_hidden_statuses: Vec<MediaStatus>,
+    pub jellyseerr_users: HashMap<String, i32>,
+    pub jellyseerr_default_user: Option<i32>,
     pub rate_limit: RateLimitConfig,
     pub omdb_api_key: Secret<String>,
     pub retry: RetryConfig,
+    pub discovery: DiscoveryConfig,
+    pub feeds: Vec<FeedConfig>,
+    pub enrichment: EnrichmentConfig,
+    pub auth: AuthConfig,
+    pub security: SecurityConfig,
 }
 
 #[derive(Clone)]
@@ -53,68 +75,207 @@ pub struct RateLimitConfig {
 }
 
 #[derive(Clone)]
-pub struct AppState {
-    pub config: Arc<AppConfig>,
+pub struct FeedState {
+    /// The feed's current settings. Admins can change them while the app runs.
+    pub config: Arc<RwLock<FeedConfig>>,
     pub releases: Arc<RwLock<Vec<Release>>>,
     pub last_update: Arc<RwLock<DateTime<Utc>>>,
 }
 
-impl AppState {
-    pub fn new(config: AppConfig) -> Self {
+impl FeedState {
+    fn new(feed: &FeedConfig) -> Self {
         Self {
-            config: Arc::new(config),
+            config: Arc::new(RwLock::new(feed.clone())),
             releases: Arc::new(RwLock::new(Vec::new())),
             last_update: Arc::new(RwLock::new(Utc::now())),
         }
     }
 }
 
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<AppConfig>,
+    pub feeds: Arc<HashMap<String, FeedState>>,
+    pub sessions: SessionSigner,
+    pub csrf: CsrfTokens,
+    pub users: Arc<UserStore>,
+    /// The `[discovery]` defaults feeds are built from, as admins last changed them.
+    pub discovery: Arc<RwLock<DiscoveryConfig>>,
+    /// Permits for enriching titles, shared by every feed refresh.
+    pub enrichment: Arc<Semaphore>,
+}
+
+impl AppState {
+    pub fn new(config: AppConfig, users: UserStore) -> Self {
+        let feeds = config
+            .feeds
+            .iter()
+            .map(|feed| (feed.id.clone(), FeedState::new(feed)))
+            .collect();
+
+        Self {
+            sessions: SessionSigner::new(&config.auth),
+            csrf: CsrfTokens::new(&config.auth),
+            enrichment: Arc::new(Semaphore::new(config.enrichment.concurrency)),
+            discovery: Arc::new(RwLock::new(config.discovery.clone())),
+            config: Arc::new(config),
+            feeds: Arc::new(feeds),
+            users: Arc::new(users),
+        }
+    }
+
+    pub fn feed(&self, id: &str) -> Option<&FeedState> {
+        self.feeds.get(id)
+    }
+
+    pub fn default_feed_id(&self) -> &str {
+        &self.config.feeds[0].id
+    }
+
+    /// Drops a title from every feed, since the same release can appear in several.
+    pub async fn remove_release(&self, id: i32, media_type: &str) {
+        for feed in self.feeds.values() {
+            let mut releases = feed.releases.write().await;
+            releases.retain(|release| !(release.id == id && release.media_type == media_type));
+        }
+    }
+
+    /// Drops requested seasons from a show, and the show once no regular season is left.
+    pub async fn remove_seasons(&self, id: i32, seasons: &[i32]) {
+        for feed in self.feeds.values() {
+            let mut releases = feed.releases.write().await;
+            releases.retain_mut(|release| {
+                if release.id != id || release.media_type != "tv" {
+                    return true;
+                }
+                release
+                    .seasons
+                    .retain(|season| !seasons.contains(&season.season_number));
+                release
+                    .seasons
+                    .iter()
+                    .any(|season| season.season_number > 0)
+            });
+        }
+    }
+}
+
 pub fn init_config() -> Result<AppConfig> {
     let settings = Settings::new()
         .map_err(|e| Error::Config(e.to_string()))?
         .validate()
         .map_err(Error::Config)?;
 
+    let feeds = settings.feeds();
+
+    // Serving HTTPS ourselves means cookies never need to travel in the clear
+    let mut auth = settings.auth;
+    if settings.server.tls().is_some() && !auth.secure_cookie {
+        tracing::info!("TLS is enabled, marking session and CSRF cookies Secure");
+        auth.secure_cookie = true;
+    }
+
     Ok(AppConfig {
         tmdb_api_key: settings.tmdb.api_key,
         jellyseerr_api_key: settings.jellyseerr.api_key,
         jellyseerr_url: settings.jellyseerr.url.clone(),
+        jellyseerr_hidden_statuses: settings.jellyseerr.hidden_statuses,
+        jellyseerr_users: settings.jellyseerr.users,
+        jellyseerr_default_user: settings.jellyseerr.default_user_id,
         rate_limit: RateLimitConfig {
             requests_per_second: settings.rate_limit.requests_per_second,
             burst_size: settings.rate_limit.burst_size,
         },
         omdb_api_key: settings.omdb.api_key,
         retry: settings.retry,
+        discovery: settings.discovery,
+        feeds,
+        enrichment: settings.enrichment,
+        auth,
+        security: settings.security,
     })
 }
 
 pub fn init_router(state: AppState) -> axum::Router {
-    use crate::api::{handlers, middleware::RateLimitServiceLayer};
-    use crate::security::headers::SecurityHeadersLayer;
+    use crate::api::{admin, handlers, middleware::RateLimitServiceLayer};
+    use crate::security::auth::{RequireRoleLayer, Role, SessionLayer, Unauthenticated};
+    use crate::security::csrf::CsrfLayer;
+    use crate::security::headers::{cors_layer, SecurityHeadersLayer};
+    use crate::security::proxy::TrustedProxyLayer;
     use axum::{
-        routing::{get, post},
+        routing::{delete, get, patch, post},
         Router,
     };
 
+    let mode = state.config.auth.mode;
+
     let static_service = ServeDir::new("static");
 
-    let api_router = Router::new()
+    let page_router = Router::new()
+        .route("/", get(handlers::index))
+        .route("/feeds/{feed}", get(handlers::feed_index))
+        .layer(SessionLayer::new(&state, Unauthenticated::Redirect));
+
+    let requester_router = Router::new()
         .route("/refresh", post(handlers::refresh))
+        .route("/refresh/{feed}", post(handlers::refresh_feed))
         .route(
             "/request/{media_type}/{id}",
             post(handlers::add_to_jellyseerr),
         )
         .route("/hide/{media_type}/{id}", post(handlers::hide_media))
+        .route_layer(CsrfLayer::new(state.csrf.clone()))
+        .route_layer(RequireRoleLayer::new(mode, Role::Requester));
+
+    let admin_router = Router::new()
+        .route(
+            "/admin/users",
+            get(admin::list_users).post(admin::create_user),
+        )
+        .route(
+            "/admin/users/{username}",
+            patch(admin::update_user).delete(admin::delete_user),
+        )
+        .route(
+            "/admin/users/{username}/sessions",
+            delete(admin::revoke_sessions),
+        )
+        .route("/admin/settings", get(admin::get_settings))
+        .route("/admin/settings/discovery", patch(admin::update_discovery))
+        .route("/admin/settings/feeds/{feed}", patch(admin::update_feed))
+        .route_layer(CsrfLayer::new(state.csrf.clone()))
+        .route_layer(RequireRoleLayer::new(mode, Role::Admin));
+
+    let api_router = Router::new()
+        .route("/releases", get(handlers::releases))
+        .route("/releases/{feed}", get(handlers::feed_releases))
+        .route("/csrf-token", get(handlers::csrf_token))
+        .merge(requester_router)
+        .merge(admin_router)
+        .layer(SessionLayer::new(&state, Unauthenticated::Reject))
+        .layer(RateLimitServiceLayer::new(
+            state.config.rate_limit.requests_per_second,
+            state.config.rate_limit.burst_size,
+        ))
+        // Outermost, so preflight requests are answered before sign-in is checked
+        .layer(cors_layer(&state.config.security.cors));
+
+    // Rate limited like the API to slow down password guessing
+    let login_router = Router::new()
+        .route("/login", get(handlers::login_page).post(handlers::login))
+        .route("/logout", post(handlers::logout))
         .layer(RateLimitServiceLayer::new(
             state.config.rate_limit.requests_per_second,
             state.config.rate_limit.burst_size,
         ));
 
     Router::new()
-        .route("/", get(handlers::index))
+        .merge(page_router)
+        .merge(login_router)
         .nest("/api", api_router)
         .nest_service("/static", static_service)
-        .layer(SecurityHeadersLayer::new())
+        .layer(SecurityHeadersLayer::new(&state.config.security))
+        .layer(TrustedProxyLayer::new(mode, &state.config.auth.proxy))
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
@@ -6,16 +6,29 @@ async fn main() -> Result<()> {
     let settings = config::setup::load_settings()?;
     config::setup::init_logging(&settings);
 
-    let config = init_config()?;
-    let state = AppState::new(config);
+    let mut config = init_config()?;
 
-    config::setup::setup_background_tasks(&state, &settings).await?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return config::setup::run_command(&config, &args).await;
+    }
+
+    let users = config::setup::load_user_store(&config).await?;
+    config::setup::resolve_watch_providers(&mut config).await?;
+    let state = AppState::new(config, users);
+
+    config::setup::setup_background_tasks(&state).await?;
 
     let app = init_router(state.clone());
     let addr = config::setup::get_socket_addr(&settings)?;
 
     info!(
-        "Server running on http://{}:{} in {} mode",
+        "Server running on {}://{}:{} in {} mode",
+        if settings.server.tls().is_some() {
+            "https"
+        } else {
+            "http"
+        },
         settings.server.host,
         settings.server.port,
         if settings.is_development() {
@@ -25,7 +38,7 @@ async fn main() -> Result<()> {
         }
     );
 
-    config::setup::setup_server(app, addr).await?;
+    config::setup::setup_server(app, addr, &settings.server).await?;
 
     Ok(())
 }
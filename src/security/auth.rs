@@ -0,0 +1,383 @@
+use axum::extract::OptionalFromRequestParts;
+use axum::response::{IntoResponse, Redirect};
+use axum::{body::Body, response::Response, Json};
+use http::request::Parts;
+use http::{HeaderMap, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tower::Service;
+use tracing::{debug, warn};
+
+use super::session::SessionSigner;
+use super::users::UserStore;
+use crate::api::jellyseerr;
+use crate::{AppConfig, AppState, AuthMode};
+
+// Jellyseerr permission bits, see server/lib/permissions.ts
+const PERMISSION_ADMIN: u32 = 2;
+const PERMISSION_REQUEST: u32 = 32;
+const PERMISSION_REQUEST_MOVIE: u32 = 262_144;
+const PERMISSION_REQUEST_TV: u32 = 524_288;
+
+/// What a signed-in user may do. Each role can do everything the ones before it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Browse feeds only.
+    #[default]
+    Viewer,
+    /// Request media, refresh feeds and hide titles.
+    Requester,
+    /// Manage users and settings.
+    Admin,
+}
+
+impl Role {
+    pub fn from_jellyseerr_permissions(permissions: u32) -> Self {
+        if permissions & PERMISSION_ADMIN != 0 {
+            Role::Admin
+        } else if permissions
+            & (PERMISSION_REQUEST | PERMISSION_REQUEST_MOVIE | PERMISSION_REQUEST_TV)
+            != 0
+        {
+            Role::Requester
+        } else {
+            Role::Viewer
+        }
+    }
+}
+
+/// The person a request is made on behalf of. Authentication layers put it in
+/// the request extensions; handlers take it as `Option<CurrentUser>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrentUser {
+    pub username: String,
+    pub email: Option<String>,
+    /// Known up front when the user signed in through Jellyseerr itself.
+    pub jellyseerr_id: Option<i32>,
+    /// Jellyseerr permission bits, for users who signed in through Jellyseerr.
+    pub jellyseerr_permissions: Option<u32>,
+    #[serde(default)]
+    pub role: Role,
+}
+
+impl CurrentUser {
+    pub fn can_request(&self, media_type: &str) -> bool {
+        if self.role < Role::Requester {
+            return false;
+        }
+        let Some(permissions) = self.jellyseerr_permissions else {
+            return true;
+        };
+        let media_permission = if media_type == "tv" {
+            PERMISSION_REQUEST_TV
+        } else {
+            PERMISSION_REQUEST_MOVIE
+        };
+
+        permissions & (PERMISSION_ADMIN | PERMISSION_REQUEST | media_permission) != 0
+    }
+}
+
+impl<S> OptionalFromRequestParts<S> for CurrentUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<CurrentUser>().cloned())
+    }
+}
+
+/// What to answer a request that needs a session but has none.
+#[derive(Clone, Copy)]
+pub enum Unauthenticated {
+    /// Send browsers to the login page.
+    Redirect,
+    /// Answer API calls with a 401.
+    Reject,
+}
+
+// Define the middleware as a service
+#[derive(Clone)]
+pub struct SessionMiddleware<S> {
+    inner: S,
+    layer: SessionLayer,
+}
+
+impl<S> Service<Request<Body>> for SessionMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let layer = self.layer.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            if let Some(user) = layer.current_user(request.headers()).await {
+                request.extensions_mut().insert(user);
+                return inner.call(request).await;
+            }
+
+            if layer.mode == AuthMode::None {
+                return inner.call(request).await;
+            }
+
+            debug!("No valid session for {}", request.uri().path());
+            // The proxy signs users in, there is no login page to send them to
+            let on_missing = if layer.mode == AuthMode::Proxy {
+                Unauthenticated::Reject
+            } else {
+                layer.on_missing
+            };
+            Ok(match on_missing {
+                Unauthenticated::Redirect => Redirect::to("/login").into_response(),
+                Unauthenticated::Reject => (
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({
+                        "success": false,
+                        "error": "Authentication required"
+                    })),
+                )
+                    .into_response(),
+            })
+        })
+    }
+}
+
+/// Resolves the session cookie, or in proxy mode the proxy's user header, into
+/// a `CurrentUser`, and unless auth is disabled, turns away requests without one.
+#[derive(Clone)]
+pub struct SessionLayer {
+    config: Arc<AppConfig>,
+    sessions: SessionSigner,
+    users: Arc<UserStore>,
+    mode: AuthMode,
+    on_missing: Unauthenticated,
+}
+
+impl SessionLayer {
+    pub fn new(state: &AppState, on_missing: Unauthenticated) -> Self {
+        Self {
+            config: state.config.clone(),
+            sessions: state.sessions.clone(),
+            users: state.users.clone(),
+            mode: state.config.auth.mode,
+            on_missing,
+        }
+    }
+
+    /// Local accounts are looked up again on every request, so that deleting a
+    /// user, changing their role or revoking their sessions applies at once.
+    /// Jellyseerr permissions are re-read every few minutes.
+    async fn current_user(&self, headers: &HeaderMap) -> Option<CurrentUser> {
+        if self.mode == AuthMode::Proxy {
+            return self.config.auth.proxy.user(headers);
+        }
+
+        let (mut user, version) = self.sessions.verify(headers)?;
+        match self.mode {
+            AuthMode::Local => {
+                let account = self.users.get(&user.username).await?;
+                if account.session_version != version {
+                    debug!("Rejected revoked session for {}", user.username);
+                    return None;
+                }
+                user.role = account.role;
+            }
+            AuthMode::Jellyseerr => {
+                let id = user.jellyseerr_id?;
+                match jellyseerr::user_permissions(&self.config, id, user.jellyseerr_permissions)
+                    .await
+                {
+                    Ok(Some(permissions)) => {
+                        user.jellyseerr_permissions = Some(permissions);
+                        user.role = Role::from_jellyseerr_permissions(permissions);
+                    }
+                    Ok(None) => {
+                        debug!(
+                            "Rejected session for {}: no longer a Jellyseerr user",
+                            user.username
+                        );
+                        return None;
+                    }
+                    Err(e) => warn!(
+                        "Could not recheck {} with Jellyseerr, keeping their session's permissions: {}",
+                        user.username, e
+                    ),
+                }
+            }
+            AuthMode::None | AuthMode::Proxy => {}
+        }
+        Some(user)
+    }
+}
+
+impl<S> tower::Layer<S> for SessionLayer {
+    type Service = SessionMiddleware<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SessionMiddleware {
+            inner: service,
+            layer: self.clone(),
+        }
+    }
+}
+
+// Define the middleware as a service
+#[derive(Clone)]
+pub struct RequireRoleMiddleware<S> {
+    inner: S,
+    layer: RequireRoleLayer,
+}
+
+impl<S> Service<Request<Body>> for RequireRoleMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let layer = self.layer.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            if layer.mode == AuthMode::None {
+                return inner.call(request).await;
+            }
+
+            let (status, error) = match request.extensions().get::<CurrentUser>() {
+                Some(user) if user.role >= layer.role => return inner.call(request).await,
+                Some(user) => {
+                    warn!(
+                        "{} ({:?}) denied {} which needs {:?}",
+                        user.username,
+                        user.role,
+                        request.uri().path(),
+                        layer.role
+                    );
+                    (StatusCode::FORBIDDEN, "You are not allowed to do this")
+                }
+                None => (StatusCode::UNAUTHORIZED, "Authentication required"),
+            };
+
+            Ok((
+                status,
+                Json(json!({
+                    "success": false,
+                    "error": error
+                })),
+            )
+                .into_response())
+        })
+    }
+}
+
+/// Only lets through signed-in users with at least `role`. Must sit inside a
+/// `SessionLayer`, which resolves the user.
+#[derive(Clone)]
+pub struct RequireRoleLayer {
+    mode: AuthMode,
+    role: Role,
+}
+
+impl RequireRoleLayer {
+    pub fn new(mode: AuthMode, role: Role) -> Self {
+        Self { mode, role }
+    }
+}
+
+impl<S> tower::Layer<S> for RequireRoleLayer {
+    type Service = RequireRoleMiddleware<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RequireRoleMiddleware {
+            inner: service,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use axum::{routing::get, Router};
+
+    /// A Jellyseerr that answers every user lookup with `status`.
+    async fn jellyseerr(status: StatusCode) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route("/api/v1/user/{id}", get(move || async move { status }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    async fn recheck(status: StatusCode, id: i32) -> Option<CurrentUser> {
+        let mut state = testing::state(AuthMode::Jellyseerr);
+        let mut config = (*state.config).clone();
+        config.jellyseerr_url = jellyseerr(status).await;
+        state.config = Arc::new(config);
+
+        let user = CurrentUser {
+            username: "alice".into(),
+            email: None,
+            jellyseerr_id: Some(id),
+            jellyseerr_permissions: Some(PERMISSION_REQUEST),
+            role: Role::Requester,
+        };
+        let cookie = state.sessions.issue(&user, 0);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::COOKIE,
+            cookie.split(';').next().unwrap().parse().unwrap(),
+        );
+
+        SessionLayer::new(&state, Unauthenticated::Reject)
+            .current_user(&headers)
+            .await
+    }
+
+    #[tokio::test]
+    async fn keeps_sessions_when_jellyseerr_fails() {
+        let user = recheck(StatusCode::INTERNAL_SERVER_ERROR, 9001).await;
+        assert_eq!(user.map(|user| user.role), Some(Role::Requester));
+    }
+
+    #[tokio::test]
+    async fn rejects_sessions_of_removed_users() {
+        assert!(recheck(StatusCode::NOT_FOUND, 9002).await.is_none());
+    }
+}
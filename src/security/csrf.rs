@@ -1,21 +1,373 @@
-use crate::error::{Error, Result};
+use crate::error::Error;
+use crate::security::session::cookie_value;
+use crate::AuthConfig;
+use axum::response::IntoResponse;
+use axum::{body::Body, response::Response, Json};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use http::HeaderMap;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderValue, Request, StatusCode};
 use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use sha2::Sha256;
+use std::sync::Arc;
+use tower::Service;
+use tracing::{debug, warn};
 
-pub fn generate_csrf_token() -> String {
-    let mut buffer = [0u8; 32];
+type HmacSha256 = Hmac<Sha256>;
+
+pub const CSRF_COOKIE: &str = "tmdb2seer_csrf";
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// How long a token is accepted. Every accepted request hands out a fresh one,
+/// and pages open for longer fetch one from `/api/csrf-token`.
+const TOKEN_TTL_SECS: i64 = 30 * 60;
+
+fn random_b64<const N: usize>() -> String {
+    let mut buffer = [0u8; N];
     rand::thread_rng().fill_bytes(&mut buffer);
-    URL_SAFE_NO_PAD.encode(&buffer)
+    URL_SAFE_NO_PAD.encode(buffer)
+}
+
+/// Signed double-submit CSRF tokens. The browser holds a random ID in an
+/// HttpOnly cookie, and tokens are `expires.nonce.signature` with an
+/// HMAC-SHA256 over that ID, the expiry and the nonce, so a token is only
+/// good together with the cookie it was issued for, and only until it expires.
+#[derive(Clone)]
+pub struct CsrfTokens {
+    key: Arc<Secret<Vec<u8>>>,
+    secure: bool,
+}
+
+impl CsrfTokens {
+    /// Derives the key from the session secret so tokens survive a restart,
+    /// or picks a random one when there is none.
+    pub fn new(config: &AuthConfig) -> Self {
+        let secret = config.session_secret.expose_secret();
+        let key = if secret.is_empty() {
+            let mut key = vec![0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            key
+        } else {
+            let mut mac =
+                HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+            mac.update(b"tmdb2seer csrf");
+            mac.finalize().into_bytes().to_vec()
+        };
+
+        Self {
+            key: Arc::new(Secret::new(key)),
+            secure: config.secure_cookie,
+        }
+    }
+
+    fn mac(&self, binding: &str, expires: i64, nonce: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(self.key.expose_secret())
+            .expect("HMAC accepts any key length");
+        mac.update(format!("{}|{}|{}", binding, expires, nonce).as_bytes());
+        mac
+    }
+
+    /// The browser's CSRF cookie ID, plus the `Set-Cookie` value to send when
+    /// it has none yet.
+    pub fn binding(&self, headers: &HeaderMap) -> (String, Option<String>) {
+        match cookie_value(headers, CSRF_COOKIE).filter(|value| !value.is_empty()) {
+            Some(binding) => (binding.to_string(), None),
+            None => {
+                let binding = random_b64::<32>();
+                let cookie = self.cookie(&binding, None);
+                (binding, Some(cookie))
+            }
+        }
+    }
+
+    /// Builds the `Set-Cookie` value that drops the CSRF cookie, so the next
+    /// page gets a new one. Sent whenever someone signs in or out.
+    pub fn clear(&self) -> String {
+        self.cookie("", Some(0))
+    }
+
+    fn cookie(&self, value: &str, max_age: Option<i64>) -> String {
+        format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict{}{}",
+            CSRF_COOKIE,
+            value,
+            max_age
+                .map(|age| format!("; Max-Age={}", age))
+                .unwrap_or_default(),
+            if self.secure { "; Secure" } else { "" }
+        )
+    }
+
+    pub fn issue(&self, binding: &str) -> String {
+        self.issue_at(binding, Utc::now().timestamp() + TOKEN_TTL_SECS)
+    }
+
+    fn issue_at(&self, binding: &str, expires: i64) -> String {
+        let nonce = random_b64::<16>();
+        let signature = self.mac(binding, expires, &nonce).finalize().into_bytes();
+        format!(
+            "{}.{}.{}",
+            expires,
+            nonce,
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Checks the `X-CSRF-Token` header against the CSRF cookie, returning
+    /// the cookie ID it is bound to.
+    pub fn verify<'a>(&self, headers: &'a HeaderMap) -> crate::Result<&'a str> {
+        let token = headers
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok());
+        self.verify_token(headers, token)
+    }
+
+    /// Checks a token posted in a plain HTML form, as the sign-in and sign-out
+    /// forms do, against the CSRF cookie.
+    pub fn verify_form<'a>(&self, headers: &'a HeaderMap, token: &str) -> crate::Result<&'a str> {
+        self.verify_token(headers, Some(token))
+    }
+
+    fn verify_token<'a>(
+        &self,
+        headers: &'a HeaderMap,
+        token: Option<&str>,
+    ) -> crate::Result<&'a str> {
+        let binding = cookie_value(headers, CSRF_COOKIE)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| Error::CSRF("Missing CSRF cookie".into()))?;
+        let token = token
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| Error::CSRF("Missing CSRF token".into()))?;
+
+        let mut parts = token.splitn(3, '.');
+        let (Some(expires), Some(nonce), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(Error::CSRF("Malformed CSRF token".into()));
+        };
+        let expires: i64 = expires
+            .parse()
+            .map_err(|_| Error::CSRF("Malformed CSRF token".into()))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| Error::CSRF("Malformed CSRF token".into()))?;
+
+        // verify_slice compares in constant time
+        self.mac(binding, expires, nonce)
+            .verify_slice(&signature)
+            .map_err(|_| Error::CSRF("Invalid CSRF token".into()))?;
+        if expires <= Utc::now().timestamp() {
+            return Err(Error::CSRF("Expired CSRF token".into()));
+        }
+
+        Ok(binding)
+    }
+}
+
+// Define the middleware as a service
+#[derive(Clone)]
+pub struct CsrfMiddleware<S> {
+    inner: S,
+    tokens: CsrfTokens,
+}
+
+impl<S> Service<Request<Body>> for CsrfMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let tokens = self.tokens.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            if request.method().is_safe() {
+                return inner.call(request).await;
+            }
+
+            let binding = match tokens.verify(request.headers()) {
+                Ok(binding) => binding.to_string(),
+                Err(e) => {
+                    warn!(
+                        "Rejected {} {}: {}",
+                        request.method(),
+                        request.uri().path(),
+                        e
+                    );
+                    return Ok((
+                        StatusCode::FORBIDDEN,
+                        Json(json!({
+                            "success": false,
+                            "error": e.to_string()
+                        })),
+                    )
+                        .into_response());
+                }
+            };
+
+            // Rotate: the page swaps in the fresh token for its next call
+            let mut response = inner.call(request).await?;
+            if let Ok(token) = HeaderValue::from_str(&tokens.issue(&binding)) {
+                response.headers_mut().insert(CSRF_HEADER, token);
+            }
+            debug!("Rotated CSRF token");
+            Ok(response)
+        })
+    }
+}
+
+/// Requires a valid CSRF token on every state-changing request.
+#[derive(Clone)]
+pub struct CsrfLayer {
+    tokens: CsrfTokens,
+}
+
+impl CsrfLayer {
+    pub fn new(tokens: CsrfTokens) -> Self {
+        Self { tokens }
+    }
+}
+
+impl<S> tower::Layer<S> for CsrfLayer {
+    type Service = CsrfMiddleware<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        CsrfMiddleware {
+            inner: service,
+            tokens: self.tokens.clone(),
+        }
+    }
 }
 
-pub fn validate_csrf_token(headers: &HeaderMap) -> Result<()> {
-    if let Some(token) = headers.get("X-CSRF-Token") {
-        if token.is_empty() {
-            return Err(Error::CSRF("Empty CSRF token received".into()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{init_router, AppState};
+    use http::header::COOKIE;
+    use tower::ServiceExt;
+
+    fn state() -> AppState {
+        crate::testing::state(crate::AuthMode::None)
+    }
+
+    fn post(uri: &str, cookie: &str, token: &str) -> Request<Body> {
+        Request::post(uri)
+            .header(COOKIE, format!("{}={}", CSRF_COOKIE, cookie))
+            .header(CSRF_HEADER, token)
+            .header("Content-Type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap()
+    }
+
+    const ENDPOINTS: [&str; 3] = ["/api/refresh", "/api/request/movie/1", "/api/hide/movie/1"];
+
+    #[tokio::test]
+    async fn rejects_forged_tokens() {
+        let state = state();
+        let forged = CsrfTokens::new(&AuthConfig {
+            session_secret: Secret::new("other-secret".into()),
+            ..AuthConfig::default()
+        })
+        .issue("browser");
+        let foreign = state.csrf.issue("someone-else");
+
+        for uri in ENDPOINTS {
+            for token in ["", "anything", forged.as_str(), foreign.as_str()] {
+                let response = init_router(state.clone())
+                    .oneshot(post(uri, "browser", token))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    response.status(),
+                    StatusCode::FORBIDDEN,
+                    "{} {:?}",
+                    uri,
+                    token
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_stale_tokens() {
+        let state = state();
+        let stale = state.csrf.issue_at("browser", Utc::now().timestamp() - 1);
+
+        for uri in ENDPOINTS {
+            let response = init_router(state.clone())
+                .oneshot(post(uri, "browser", &stale))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", uri);
         }
-        Ok(())
-    } else {
-        Err(Error::CSRF("Missing CSRF token".into()))
+    }
+
+    #[tokio::test]
+    async fn hands_out_tokens_for_the_cookie() {
+        let state = state();
+        let response = init_router(state.clone())
+            .oneshot(
+                Request::get("/api/csrf-token")
+                    .header(COOKIE, format!("{}=browser", CSRF_COOKIE))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let response = init_router(state)
+            .oneshot(post(
+                "/api/hide/movie/1",
+                "browser",
+                body["token"].as_str().unwrap(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn accepts_and_rotates_valid_tokens() {
+        let state = state();
+        let token = state.csrf.issue("browser");
+
+        let response = init_router(state.clone())
+            .oneshot(post("/api/hide/movie/1", "browser", &token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let rotated = response
+            .headers()
+            .get(CSRF_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert_ne!(rotated, token);
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static("tmdb2seer_csrf=browser"));
+        headers.insert(CSRF_HEADER, HeaderValue::from_str(rotated).unwrap());
+        assert_eq!(state.csrf.verify(&headers).unwrap(), "browser");
     }
 }
@@ -1,9 +1,73 @@
+use ipnet::IpNet;
 use secrecy::Secret;
 use serde::Deserialize;
+use std::net::IpAddr;
+
+/// Prefix that makes a secret setting name a file to read the secret from,
+/// e.g. `api_key = "file:/run/secrets/tmdb_api_key"`.
+const FILE_PREFIX: &str = "file:";
+
+/// Reads a secret from a mounted file, dropping the trailing newline most
+/// editors and `echo` leave behind.
+pub fn read_secret_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map(|secret| secret.trim_end().to_string())
+        .map_err(|e| format!("cannot read secret file {}: {}", path, e))
+}
 
 pub fn deserialize_secret_string<'de, D>(deserializer: D) -> Result<Secret<String>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    String::deserialize(deserializer).map(Secret::new)
+    let value = String::deserialize(deserializer)?;
+    match value.strip_prefix(FILE_PREFIX) {
+        Some(path) => read_secret_file(path.trim())
+            .map(Secret::new)
+            .map_err(serde::de::Error::custom),
+        None => Ok(Secret::new(value)),
+    }
+}
+
+/// Parses networks in CIDR notation, taking a bare address as a network of one.
+pub fn deserialize_networks<'de, D>(deserializer: D) -> Result<Vec<IpNet>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|network| {
+            let network = network.trim();
+            network
+                .parse::<IpNet>()
+                .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| serde::de::Error::custom(format!("invalid network: {}", network)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+    use serde::de::value::{Error, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    fn secret(value: &str) -> Result<Secret<String>, Error> {
+        let deserializer: StrDeserializer<Error> = value.into_deserializer();
+        deserialize_secret_string(deserializer)
+    }
+
+    #[test]
+    fn reads_secrets_from_files() {
+        let path = std::env::temp_dir().join(format!("tmdb2seer-secret-{}", std::process::id()));
+        std::fs::write(&path, "s3cret \n").unwrap();
+
+        let value = secret(&format!("file:{}", path.display())).unwrap();
+        assert_eq!(value.expose_secret(), "s3cret");
+        assert_eq!(secret("plain").unwrap().expose_secret(), "plain");
+
+        std::fs::remove_file(&path).unwrap();
+        let error = secret(&format!("file:{}", path.display())).unwrap_err();
+        assert!(error.to_string().contains("cannot read secret file"));
+    }
 }
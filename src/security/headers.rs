@@ -1,15 +1,119 @@
+use axum::extract::FromRequestParts;
 use axum::response::Response;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use futures::future::BoxFuture;
-use http::{HeaderValue, Request};
+use http::request::Parts;
+use http::{HeaderValue, Method, Request, StatusCode};
+use rand::RngCore;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use tower::{Layer, Service};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::api::tmdb;
+use crate::config::settings::{CorsConfig, FrameOptions};
+use crate::SecurityConfig;
+
+/// A fresh value per response that inline scripts and styles must carry to
+/// pass the Content-Security-Policy. `SecurityHeaders` puts it in the request
+/// extensions; handlers take it as an extractor and hand it to their template.
+#[derive(Debug, Clone)]
+pub struct CspNonce(pub String);
+
+impl CspNonce {
+    fn generate() -> Self {
+        let mut buffer = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut buffer);
+        Self(STANDARD.encode(buffer))
+    }
+
+    fn policy(&self, frame_ancestors: &str) -> String {
+        format!(
+            "default-src 'self'; \
+             script-src 'self' 'nonce-{nonce}'; \
+             style-src 'self' 'nonce-{nonce}'; \
+             img-src 'self' {image_host}; \
+             connect-src 'self'; \
+             object-src 'none'; \
+             base-uri 'self'; \
+             form-action 'self'; \
+             frame-ancestors {frame_ancestors}",
+            nonce = self.0,
+            image_host = tmdb::IMAGE_HOST
+        )
+    }
+}
+
+impl<S> FromRequestParts<S> for CspNonce
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CspNonce>()
+            .cloned()
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Missing CSP nonce"))
+    }
+}
+
+/// Header values worked out once from `[security]`.
+struct HeaderSet {
+    frame_options: HeaderValue,
+    frame_ancestors: &'static str,
+    referrer_policy: Option<HeaderValue>,
+    cache_control: Option<HeaderValue>,
+    hsts: Option<HeaderValue>,
+}
+
+impl HeaderSet {
+    fn new(config: &SecurityConfig) -> Self {
+        let (frame_options, frame_ancestors) = match config.frame_options {
+            FrameOptions::Deny => ("DENY", "'none'"),
+            FrameOptions::SameOrigin => ("SAMEORIGIN", "'self'"),
+        };
+
+        let hsts = config.hsts.enabled.then(|| {
+            let mut value = format!("max-age={}", config.hsts.max_age);
+            if config.hsts.include_subdomains {
+                value.push_str("; includeSubDomains");
+            }
+            if config.hsts.preload {
+                value.push_str("; preload");
+            }
+            HeaderValue::from_str(&value).expect("HSTS value is plain ASCII")
+        });
+
+        Self {
+            frame_options: HeaderValue::from_static(frame_options),
+            frame_ancestors,
+            referrer_policy: HeaderValue::from_str(&config.referrer_policy).ok(),
+            cache_control: Some(config.cache_control.as_str())
+                .filter(|value| !value.is_empty())
+                .and_then(|value| HeaderValue::from_str(value).ok()),
+            hsts,
+        }
+    }
+}
 
 #[derive(Clone)]
-pub struct SecurityHeadersLayer;
+pub struct SecurityHeadersLayer {
+    headers: Arc<HeaderSet>,
+}
 
 impl SecurityHeadersLayer {
-    pub fn new() -> Self {
-        SecurityHeadersLayer
+    pub fn new(config: &SecurityConfig) -> Self {
+        SecurityHeadersLayer {
+            headers: Arc::new(HeaderSet::new(config)),
+        }
+    }
+}
+
+impl Default for SecurityHeadersLayer {
+    fn default() -> Self {
+        Self::new(&SecurityConfig::default())
     }
 }
 
@@ -17,13 +121,17 @@ impl<S> Layer<S> for SecurityHeadersLayer {
     type Service = SecurityHeaders<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        SecurityHeaders { inner: service }
+        SecurityHeaders {
+            inner: service,
+            headers: self.headers.clone(),
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct SecurityHeaders<S> {
     inner: S,
+    headers: Arc<HeaderSet>,
 }
 
 impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SecurityHeaders<S>
@@ -41,9 +149,14 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
         let clone = self.inner.clone();
         let mut inner = std::mem::replace(&mut self.inner, clone);
+        let set = self.headers.clone();
+
+        let nonce = CspNonce::generate();
+        request.extensions_mut().insert(nonce.clone());
+        let is_static = request.uri().path().starts_with("/static/");
 
         Box::pin(async move {
             let mut response = inner.call(request).await?;
@@ -55,35 +168,177 @@ where
                 HeaderValue::from_static("nosniff"),
             );
 
-            headers.insert("X-Frame-Options", HeaderValue::from_static("SAMEORIGIN"));
+            headers.insert("X-Frame-Options", set.frame_options.clone());
 
             headers.insert(
                 "X-XSS-Protection",
                 HeaderValue::from_static("1; mode=block"),
             );
 
-            headers.insert(
-                "Content-Security-Policy",
-                HeaderValue::from_static(
-                    "default-src 'self'; \
-                     script-src 'self' 'unsafe-inline'; \
-                     style-src 'self' 'unsafe-inline'; \
-                     img-src 'self' https://image.tmdb.org https:; \
-                     connect-src 'self' https:;",
-                ),
-            );
+            if let Ok(policy) = HeaderValue::from_str(&nonce.policy(set.frame_ancestors)) {
+                headers.insert("Content-Security-Policy", policy);
+            }
 
-            headers.insert(
-                "Referrer-Policy",
-                HeaderValue::from_static("strict-origin-when-cross-origin"),
-            );
+            if let Some(referrer_policy) = &set.referrer_policy {
+                headers.insert("Referrer-Policy", referrer_policy.clone());
+            }
 
-            headers.insert(
-                "Cache-Control",
-                HeaderValue::from_static("no-store, max-age=0"),
-            );
+            if let Some(hsts) = &set.hsts {
+                headers.insert("Strict-Transport-Security", hsts.clone());
+            }
+
+            if let Some(cache_control) = set.cache_control.as_ref().filter(|_| !is_static) {
+                headers.insert("Cache-Control", cache_control.clone());
+            }
 
             Ok(response)
         })
     }
 }
+
+/// Lets the configured origins read `/api` from the browser. With no origins
+/// configured it answers no cross-origin request. Writes stay same-origin,
+/// since the CSRF token and its `SameSite=Strict` cookie never reach other sites.
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let origins: Vec<HeaderValue> = config
+        .allowed_origins
+        .iter()
+        .filter_map(|origin| HeaderValue::from_str(origin).ok())
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET])
+        .allow_credentials(config.allow_credentials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn hands_the_policy_nonce_to_handlers() {
+        let app = Router::new()
+            .route("/", get(|nonce: CspNonce| async move { nonce.0 }))
+            .layer(SecurityHeadersLayer::default());
+
+        let response = app
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let policy = response.headers()["Content-Security-Policy"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let nonce = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(policy.contains(&format!("script-src 'self' 'nonce-{}'", nonce)));
+        assert!(!policy.contains("unsafe-inline"));
+    }
+
+    #[test]
+    fn allows_every_image_the_app_emits() {
+        let policy = CspNonce::generate().policy("'none'");
+        let sources: Vec<&str> = policy
+            .split("; ")
+            .find_map(|directive| directive.strip_prefix("img-src "))
+            .unwrap()
+            .split(' ')
+            .collect();
+        let allowed = |url: &str| {
+            if url.starts_with('/') {
+                sources.contains(&"'self'")
+            } else {
+                sources
+                    .iter()
+                    .any(|source| url.starts_with(&format!("{}/", source)))
+            }
+        };
+
+        for url in [
+            tmdb::poster_url(Some("/poster.jpg")),
+            tmdb::poster_url(None),
+            tmdb::image_url("w185", "/season.jpg"),
+            tmdb::image_url("w92", "/logo.png"),
+        ] {
+            assert!(allowed(&url), "{} is blocked by {}", url, policy);
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_configured_headers() {
+        let mut config = SecurityConfig {
+            frame_options: FrameOptions::Deny,
+            ..SecurityConfig::default()
+        };
+        config.hsts.enabled = true;
+        config.hsts.include_subdomains = true;
+        config.hsts.preload = true;
+        config.cors.allowed_origins = vec!["https://dashboard.example.com".into()];
+
+        let app = Router::new()
+            .route("/api/releases", get(|| async { "[]" }))
+            .route("/static/app.js", get(|| async { "" }))
+            .layer(cors_layer(&config.cors))
+            .layer(SecurityHeadersLayer::new(&config));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/releases")
+                    .header("Origin", "https://dashboard.example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(
+            headers["Strict-Transport-Security"],
+            "max-age=31536000; includeSubDomains; preload"
+        );
+        assert_eq!(headers["X-Frame-Options"], "DENY");
+        assert!(headers["Content-Security-Policy"]
+            .to_str()
+            .unwrap()
+            .ends_with("frame-ancestors 'none'"));
+        assert_eq!(headers["Cache-Control"], "no-store, max-age=0");
+        assert_eq!(
+            headers["Access-Control-Allow-Origin"],
+            "https://dashboard.example.com"
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/static/app.js")
+                    .header("Origin", "https://evil.example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(response.headers().get("Cache-Control").is_none());
+        assert!(response
+            .headers()
+            .get("Access-Control-Allow-Origin")
+            .is_none());
+
+        let preflight = app
+            .oneshot(
+                Request::options("/api/releases")
+                    .header("Origin", "https://dashboard.example.com")
+                    .header("Access-Control-Request-Method", "POST")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(preflight.headers()["Access-Control-Allow-Methods"], "GET");
+    }
+}
@@ -0,0 +1,167 @@
+use axum::response::IntoResponse;
+use axum::{body::Body, extract::ConnectInfo, response::Response};
+use http::{HeaderMap, Request, StatusCode};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tower::Service;
+use tracing::warn;
+
+use super::auth::CurrentUser;
+use crate::{AuthMode, ProxyAuthConfig};
+
+impl ProxyAuthConfig {
+    pub fn trusts(&self, ip: IpAddr) -> bool {
+        // Dual-stack listeners see IPv4 peers as IPv4-mapped IPv6 addresses
+        let ip = ip.to_canonical();
+        self.trusted_proxies
+            .iter()
+            .any(|network| network.contains(&ip))
+    }
+
+    /// The user the proxy signed in, if it sent one.
+    pub fn user(&self, headers: &HeaderMap) -> Option<CurrentUser> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        Some(CurrentUser {
+            username: header(&self.user_header)?,
+            email: header(&self.email_header),
+            jellyseerr_id: None,
+            jellyseerr_permissions: None,
+            role: self.role,
+        })
+    }
+}
+
+// Define the middleware as a service
+#[derive(Clone)]
+pub struct TrustedProxyMiddleware<S> {
+    inner: S,
+    layer: TrustedProxyLayer,
+}
+
+impl<S> Service<Request<Body>> for TrustedProxyMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let layer = self.layer.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            if layer.mode != AuthMode::Proxy {
+                return inner.call(request).await;
+            }
+
+            let peer = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+
+            match peer {
+                Some(ip) if layer.proxy.trusts(ip) => inner.call(request).await,
+                _ => {
+                    warn!(
+                        "Refused {} from untrusted address {:?}",
+                        request.uri().path(),
+                        peer
+                    );
+                    Ok((
+                        StatusCode::FORBIDDEN,
+                        "Requests must come through the authentication proxy",
+                    )
+                        .into_response())
+                }
+            }
+        })
+    }
+}
+
+/// In proxy auth mode, refuses every request that does not come from a
+/// trusted proxy, so nobody can reach the app directly with a forged user header.
+#[derive(Clone)]
+pub struct TrustedProxyLayer {
+    mode: AuthMode,
+    proxy: Arc<ProxyAuthConfig>,
+}
+
+impl TrustedProxyLayer {
+    pub fn new(mode: AuthMode, proxy: &ProxyAuthConfig) -> Self {
+        Self {
+            mode,
+            proxy: Arc::new(proxy.clone()),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for TrustedProxyLayer {
+    type Service = TrustedProxyMiddleware<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        TrustedProxyMiddleware {
+            inner: service,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::auth::Role;
+    use http::HeaderValue;
+
+    fn config() -> ProxyAuthConfig {
+        ProxyAuthConfig {
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()],
+            ..ProxyAuthConfig::default()
+        }
+    }
+
+    #[test]
+    fn trusts_only_configured_networks() {
+        let config = config();
+        assert!(config.trusts("10.1.2.3".parse().unwrap()));
+        assert!(config.trusts("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(config.trusts("::1".parse().unwrap()));
+        assert!(!config.trusts("192.168.1.10".parse().unwrap()));
+        assert!(!config.trusts("127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn reads_the_user_from_headers() {
+        let config = config();
+        let mut headers = HeaderMap::new();
+        assert!(config.user(&headers).is_none());
+
+        headers.insert("remote-user", HeaderValue::from_static(" alice "));
+        headers.insert(
+            "remote-email",
+            HeaderValue::from_static("alice@example.com"),
+        );
+        let user = config.user(&headers).unwrap();
+        assert_eq!(user.username, "alice");
+        assert_eq!(user.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(user.role, Role::Requester);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::security::auth::CurrentUser;
+use crate::security::users::write_owner_only;
+use crate::{AuthConfig, AuthMode, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use http::HeaderMap;
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SESSION_COOKIE: &str = "tmdb2seer_session";
+
+#[derive(Serialize, Deserialize)]
+struct SessionClaims {
+    user: CurrentUser,
+    expires: i64,
+    /// Local accounts bump their version to revoke every session issued before.
+    #[serde(default)]
+    version: u32,
+    /// Random per sign-in, so one session can be revoked on its own.
+    id: String,
+}
+
+/// Sessions signed out before they expired, so that a copied cookie stops
+/// working too. Written to a JSON file so a restart does not bring them back.
+struct RevokedSessions {
+    path: Option<PathBuf>,
+    /// Session ID to the time its cookie would have expired anyway.
+    ids: RwLock<HashMap<String, i64>>,
+    /// Held from updating `ids` until the file is written, so saves land in
+    /// order and never share the temp file.
+    save: Mutex<()>,
+}
+
+impl RevokedSessions {
+    fn load(path: &str) -> Self {
+        let ids = match std::fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable revoked sessions in {}: {}", path, e);
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                warn!("Could not read revoked sessions from {}: {}", path, e);
+                HashMap::new()
+            }
+        };
+
+        Self {
+            path: Some(PathBuf::from(path)),
+            ids: RwLock::new(ids),
+            save: Mutex::new(()),
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            path: None,
+            ids: RwLock::new(HashMap::new()),
+            save: Mutex::new(()),
+        }
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.ids.read().expect("lock poisoned").contains_key(id)
+    }
+
+    async fn revoke(&self, id: &str, expires: i64) -> Result<()> {
+        let _save = self.save.lock().await;
+        let ids = {
+            let mut ids = self.ids.write().expect("lock poisoned");
+            // Expired cookies are rejected anyway, so their entries can go
+            let now = Utc::now().timestamp();
+            ids.retain(|_, expires| *expires > now);
+            ids.insert(id.to_string(), expires);
+            ids.clone()
+        };
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        write_owner_only(path, serde_json::to_string(&ids)?.as_bytes()).await
+    }
+}
+
+/// Issues and checks stateless session cookies: the signed-in user and an expiry,
+/// followed by an HMAC-SHA256 signature over both.
+#[derive(Clone)]
+pub struct SessionSigner {
+    key: Arc<Secret<Vec<u8>>>,
+    ttl_secs: i64,
+    secure: bool,
+    revoked: Arc<RevokedSessions>,
+}
+
+impl SessionSigner {
+    pub fn new(config: &AuthConfig) -> Self {
+        let secret = config.session_secret.expose_secret();
+        let key = if secret.is_empty() {
+            warn!("No auth session_secret set, sessions will not survive a restart");
+            let mut key = vec![0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            key
+        } else {
+            secret.as_bytes().to_vec()
+        };
+
+        Self {
+            key: Arc::new(Secret::new(key)),
+            ttl_secs: i64::try_from(config.session_ttl_hours * 3600).unwrap_or(i64::MAX),
+            secure: config.secure_cookie,
+            revoked: Arc::new(match config.mode {
+                AuthMode::Jellyseerr | AuthMode::Local => {
+                    RevokedSessions::load(&config.revoked_sessions_file)
+                }
+                AuthMode::None | AuthMode::Proxy => RevokedSessions::empty(),
+            }),
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(self.key.expose_secret()).expect("HMAC accepts any key length")
+    }
+
+    fn sign(&self, payload: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+
+    /// Builds the `Set-Cookie` value that signs `user` in.
+    pub fn issue(&self, user: &CurrentUser, version: u32) -> String {
+        let claims = SessionClaims {
+            user: user.clone(),
+            expires: Utc::now().timestamp() + self.ttl_secs,
+            version,
+            id: random_id(),
+        };
+        let payload = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&claims).expect("session claims always serialize"));
+        let signature = self.sign(&payload);
+
+        self.cookie(&format!("{}.{}", payload, signature), self.ttl_secs)
+    }
+
+    /// Builds the `Set-Cookie` value that signs the browser out.
+    pub fn clear(&self) -> String {
+        self.cookie("", 0)
+    }
+
+    /// Rejects the request's session from now on, wherever the cookie is sent from.
+    pub async fn revoke(&self, headers: &HeaderMap) -> Result<()> {
+        let Some(claims) = self.claims(headers) else {
+            return Ok(());
+        };
+        self.revoked.revoke(&claims.id, claims.expires).await?;
+        info!("Revoked a session of {}", claims.user.username);
+        Ok(())
+    }
+
+    fn cookie(&self, value: &str, max_age: i64) -> String {
+        format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            SESSION_COOKIE,
+            value,
+            max_age,
+            if self.secure { "; Secure" } else { "" }
+        )
+    }
+
+    /// Returns the user and session version of a validly signed, unexpired
+    /// session cookie that has not been revoked.
+    pub fn verify(&self, headers: &HeaderMap) -> Option<(CurrentUser, u32)> {
+        let claims = self.claims(headers)?;
+        if self.revoked.contains(&claims.id) {
+            debug!("Rejected revoked session for {}", claims.user.username);
+            return None;
+        }
+
+        Some((claims.user, claims.version))
+    }
+
+    fn claims(&self, headers: &HeaderMap) -> Option<SessionClaims> {
+        let value = cookie_value(headers, SESSION_COOKIE)?;
+        let (payload, signature) = value.split_once('.')?;
+
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        if mac.verify_slice(&signature).is_err() {
+            debug!("Rejected session cookie with a bad signature");
+            return None;
+        }
+
+        let claims: SessionClaims =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        if claims.expires <= Utc::now().timestamp() {
+            debug!("Rejected expired session for {}", claims.user.username);
+            return None;
+        }
+
+        Some(claims)
+    }
+}
+
+fn random_id() -> String {
+    let mut buffer = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut buffer);
+    URL_SAFE_NO_PAD.encode(buffer)
+}
+
+/// Finds a cookie by name across every `Cookie` header of a request.
+pub fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::auth::Role;
+    use http::HeaderValue;
+
+    fn signer(ttl_hours: u64) -> SessionSigner {
+        SessionSigner::new(&AuthConfig {
+            mode: AuthMode::None,
+            session_secret: Secret::new("test-secret".into()),
+            session_ttl_hours: ttl_hours,
+            ..AuthConfig::default()
+        })
+    }
+
+    fn request_with(set_cookie: &str) -> HeaderMap {
+        let cookie = set_cookie.split(';').next().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {}", cookie)).unwrap(),
+        );
+        headers
+    }
+
+    fn user() -> CurrentUser {
+        CurrentUser {
+            username: "alice".into(),
+            email: None,
+            jellyseerr_id: Some(5),
+            jellyseerr_permissions: Some(32),
+            role: Role::Requester,
+        }
+    }
+
+    #[test]
+    fn accepts_its_own_sessions() {
+        let signer = signer(1);
+        let session = signer.verify(&request_with(&signer.issue(&user(), 3)));
+        assert_eq!(
+            session.map(|(user, version)| (user.username, user.role, version)),
+            Some(("alice".into(), Role::Requester, 3))
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_and_foreign_sessions() {
+        let signer = signer(1);
+        let cookie = signer.issue(&user(), 0);
+
+        let (payload, signature) = cookie.split_once('.').unwrap();
+        let tampered = format!("{}A.{}", payload, signature);
+        assert!(signer.verify(&request_with(&tampered)).is_none());
+
+        let other = SessionSigner::new(&AuthConfig {
+            session_secret: Secret::new("other-secret".into()),
+            ..AuthConfig::default()
+        });
+        assert!(other.verify(&request_with(&cookie)).is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_revoked_sessions() {
+        let signer = signer(1);
+        let first = request_with(&signer.issue(&user(), 0));
+        let second = request_with(&signer.issue(&user(), 0));
+
+        signer.revoke(&first).await.unwrap();
+        assert!(signer.verify(&first).is_none());
+        assert!(signer.verify(&second).is_some());
+    }
+
+    #[tokio::test]
+    async fn keeps_every_concurrent_revocation() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("tmdb2seer-revoked-{}", std::process::id()));
+        let path = dir.join("revoked.json");
+        let path = path.to_str().unwrap();
+        let revoked = RevokedSessions::load(path);
+        let expires = Utc::now().timestamp() + 3600;
+
+        let ids: Vec<String> = (0..20).map(|i| format!("session-{}", i)).collect();
+        let results =
+            futures::future::join_all(ids.iter().map(|id| revoked.revoke(id, expires))).await;
+        assert!(results.iter().all(Result::is_ok));
+
+        let reloaded = RevokedSessions::load(path);
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(ids.iter().all(|id| reloaded.contains(id)));
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn rejects_expired_sessions() {
+        let mut signer = signer(1);
+        signer.ttl_secs = -1;
+        assert!(signer
+            .verify(&request_with(&signer.issue(&user(), 0)))
+            .is_none());
+    }
+}
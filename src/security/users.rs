@@ -0,0 +1,378 @@
+use crate::security::auth::Role;
+use crate::{Error, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// A native account, as stored on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRecord {
+    pub username: String,
+    /// Argon2id hash in PHC string format.
+    pub password_hash: String,
+    pub role: Role,
+    /// Sessions carry the version they were issued at; bumping it signs the
+    /// user out everywhere.
+    #[serde(default)]
+    pub session_version: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What the admin API shows of an account.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserSummary {
+    pub username: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&UserRecord> for UserSummary {
+    fn from(record: &UserRecord) -> Self {
+        Self {
+            username: record.username.clone(),
+            role: record.role,
+            created_at: record.created_at,
+        }
+    }
+}
+
+/// Changes to an existing account. Fields left out stay as they are.
+#[derive(Debug, Default, Deserialize)]
+pub struct UserUpdate {
+    pub password: Option<String>,
+    pub role: Option<Role>,
+}
+
+/// Hash of a random password, never matched by anything.
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| {
+    let mut password = [0u8; 32];
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut password);
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).expect("16 bytes is a valid salt");
+    Argon2::default()
+        .hash_password(&password, &salt)
+        .expect("hashing a random password")
+        .to_string()
+});
+
+#[derive(Serialize, Deserialize, Default)]
+struct UsersFile {
+    users: Vec<UserRecord>,
+}
+
+/// Native accounts for `auth.mode = "local"`, kept in memory and written back
+/// to a JSON file on every change.
+pub struct UserStore {
+    path: Option<PathBuf>,
+    users: RwLock<HashMap<String, UserRecord>>,
+}
+
+impl UserStore {
+    /// Loads the accounts from `path`, starting empty if the file does not exist yet.
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = match tokio::fs::read_to_string(&path).await {
+            Ok(data) => serde_json::from_str::<UsersFile>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No user file at {}, starting empty", path.display());
+                UsersFile::default()
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path: Some(path),
+            users: RwLock::new(
+                file.users
+                    .into_iter()
+                    .map(|user| (user.username.clone(), user))
+                    .collect(),
+            ),
+        })
+    }
+
+    /// A store that lives in memory only, for modes without native accounts.
+    pub fn empty() -> Self {
+        Self {
+            path: None,
+            users: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.users.read().await.is_empty()
+    }
+
+    pub async fn get(&self, username: &str) -> Option<UserRecord> {
+        self.users.read().await.get(username).cloned()
+    }
+
+    pub async fn list(&self) -> Vec<UserSummary> {
+        let mut users: Vec<UserSummary> = self
+            .users
+            .read()
+            .await
+            .values()
+            .map(UserSummary::from)
+            .collect();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        users
+    }
+
+    /// Checks a username and password, returning the account they belong to.
+    /// Unknown users are checked against a dummy hash, so both failures take
+    /// as long and timing does not tell which usernames exist.
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<UserRecord> {
+        let record = self.get(username).await;
+
+        let hash = record.as_ref().map(|record| record.password_hash.clone());
+        let password = password.to_string();
+        let valid = tokio::task::spawn_blocking(move || {
+            let hash = hash.as_deref().unwrap_or(&DUMMY_HASH);
+            PasswordHash::new(hash)
+                .map(|hash| {
+                    Argon2::default()
+                        .verify_password(password.as_bytes(), &hash)
+                        .is_ok()
+                })
+                .unwrap_or(false)
+        })
+        .await
+        .map_err(|e| Error::Api(format!("Password check failed: {}", e)))?;
+
+        match record {
+            Some(record) if valid => Ok(record),
+            Some(_) => Err(Error::Unauthorized(format!(
+                "Wrong password for {}",
+                username
+            ))),
+            None => Err(Error::Unauthorized(format!("Unknown user {}", username))),
+        }
+    }
+
+    pub async fn create(&self, username: &str, password: &str, role: Role) -> Result<UserSummary> {
+        let username = username.trim();
+        if username.is_empty() {
+            return Err(Error::BadRequest("Username must not be empty".into()));
+        }
+        let password_hash = hash_password(password).await?;
+
+        let mut users = self.users.write().await;
+        if users.contains_key(username) {
+            return Err(Error::BadRequest(format!(
+                "User {} already exists",
+                username
+            )));
+        }
+
+        let record = UserRecord {
+            username: username.to_string(),
+            password_hash,
+            role,
+            session_version: 0,
+            created_at: Utc::now(),
+        };
+        let summary = UserSummary::from(&record);
+        users.insert(record.username.clone(), record);
+        self.save(&users).await?;
+
+        info!("Created {:?} account {}", role, username);
+        Ok(summary)
+    }
+
+    /// Changing the password also signs the user out of every session.
+    pub async fn update(&self, username: &str, update: UserUpdate) -> Result<UserSummary> {
+        let password_hash = match &update.password {
+            Some(password) => Some(hash_password(password).await?),
+            None => None,
+        };
+
+        let mut users = self.users.write().await;
+        let record = users
+            .get(username)
+            .ok_or_else(|| Error::BadRequest(format!("Unknown user {}", username)))?;
+
+        if let Some(role) = update.role {
+            if record.role == Role::Admin && role != Role::Admin && admin_count(&users) == 1 {
+                return Err(Error::BadRequest("Cannot demote the last admin".into()));
+            }
+        }
+
+        let record = users.get_mut(username).expect("checked above");
+        if let Some(role) = update.role {
+            record.role = role;
+        }
+        if let Some(password_hash) = password_hash {
+            record.password_hash = password_hash;
+            record.session_version += 1;
+        }
+        let summary = UserSummary::from(&*record);
+        self.save(&users).await?;
+
+        info!("Updated account {}", username);
+        Ok(summary)
+    }
+
+    pub async fn delete(&self, username: &str) -> Result<()> {
+        let mut users = self.users.write().await;
+        let record = users
+            .get(username)
+            .ok_or_else(|| Error::BadRequest(format!("Unknown user {}", username)))?;
+        if record.role == Role::Admin && admin_count(&users) == 1 {
+            return Err(Error::BadRequest("Cannot delete the last admin".into()));
+        }
+
+        users.remove(username);
+        self.save(&users).await?;
+
+        info!("Deleted account {}", username);
+        Ok(())
+    }
+
+    /// Signs a user out of every session without touching their password.
+    pub async fn revoke_sessions(&self, username: &str) -> Result<()> {
+        let mut users = self.users.write().await;
+        let record = users
+            .get_mut(username)
+            .ok_or_else(|| Error::BadRequest(format!("Unknown user {}", username)))?;
+        record.session_version += 1;
+        self.save(&users).await?;
+
+        info!("Revoked sessions of {}", username);
+        Ok(())
+    }
+
+    async fn save(&self, users: &HashMap<String, UserRecord>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut file = UsersFile {
+            users: users.values().cloned().collect(),
+        };
+        file.users.sort_by(|a, b| a.username.cmp(&b.username));
+
+        write_owner_only(path, serde_json::to_string_pretty(&file)?.as_bytes()).await?;
+
+        debug!("Saved {} accounts to {}", file.users.len(), path.display());
+        Ok(())
+    }
+}
+
+/// Replaces `path` through a temp file only the owner can read, so readers
+/// never see a half-written file. Callers serialize their writes, since every
+/// write goes through the same temp file.
+pub(crate) async fn write_owner_only(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // A leftover temp file could have other permissions, so always start a
+    // fresh one
+    let temp_path = path.with_extension("json.tmp");
+    match tokio::fs::remove_file(&temp_path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut temp = options.open(&temp_path).await?;
+    temp.write_all(contents).await?;
+    temp.sync_all().await?;
+    tokio::fs::rename(temp_path, path).await?;
+    Ok(())
+}
+
+fn admin_count(users: &HashMap<String, UserRecord>) -> usize {
+    users
+        .values()
+        .filter(|user| user.role == Role::Admin)
+        .count()
+}
+
+async fn hash_password(password: &str) -> Result<String> {
+    if password.is_empty() {
+        return Err(Error::BadRequest("Password must not be empty".into()));
+    }
+
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt).map_err(|e| Error::Api(e.to_string()))?;
+
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| Error::Api(format!("Failed to hash password: {}", e)))
+    })
+    .await
+    .map_err(|e| Error::Api(format!("Failed to hash password: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn checks_passwords_and_revokes_on_change() {
+        let store = UserStore::empty();
+        store.create("alice", "hunter2", Role::Admin).await.unwrap();
+
+        let record = store.authenticate("alice", "hunter2").await.unwrap();
+        assert_eq!(record.session_version, 0);
+        assert!(store.authenticate("alice", "wrong").await.is_err());
+        assert!(store.authenticate("bob", "hunter2").await.is_err());
+
+        let update = UserUpdate {
+            password: Some("correct horse".into()),
+            role: None,
+        };
+        store.update("alice", update).await.unwrap();
+        assert!(store.authenticate("alice", "hunter2").await.is_err());
+        let record = store.authenticate("alice", "correct horse").await.unwrap();
+        assert_eq!(record.session_version, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn writes_the_file_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("tmdb2seer-users-{}", std::process::id()));
+        let path = dir.join("users.json");
+        let store = UserStore::open(&path).await.unwrap();
+        store.create("alice", "hunter2", Role::Admin).await.unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_last_admin() {
+        let store = UserStore::empty();
+        store.create("alice", "hunter2", Role::Admin).await.unwrap();
+        store.create("bob", "hunter2", Role::Viewer).await.unwrap();
+
+        assert!(store.delete("alice").await.is_err());
+        let demote = UserUpdate {
+            password: None,
+            role: Some(Role::Requester),
+        };
+        assert!(store.update("alice", demote).await.is_err());
+
+        store.delete("bob").await.unwrap();
+        assert_eq!(store.list().await.len(), 1);
+    }
+}
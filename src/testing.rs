@@ -0,0 +1,39 @@
+//! Fixtures shared by the router tests.
+
+use crate::security::users::UserStore;
+use crate::{AppConfig, AppState, AuthConfig, AuthMode, FeedConfig, RateLimitConfig};
+use secrecy::Secret;
+
+/// App state with a single `latest` feed and no upstream services reachable.
+pub fn state(mode: AuthMode) -> AppState {
+    let config = AppConfig {
+        tmdb_api_key: Secret::new(String::new()),
+        jellyseerr_api_key: Secret::new("key".into()),
+        jellyseerr_url: "http://127.0.0.1:9".into(),
+        jellyseerr_hidden_statuses: Vec::new(),
+        jellyseerr_users: Default::default(),
+        jellyseerr_default_user: None,
+        rate_limit: RateLimitConfig {
+            requests_per_second: 100,
+            burst_size: 100,
+        },
+        omdb_api_key: Secret::new(String::new()),
+        retry: Default::default(),
+        discovery: Default::default(),
+        feeds: vec![FeedConfig {
+            id: "latest".into(),
+            name: "Latest".into(),
+            refresh_interval: 300,
+            discovery: Default::default(),
+            overrides: Default::default(),
+        }],
+        enrichment: Default::default(),
+        auth: AuthConfig {
+            mode,
+            session_secret: Secret::new("test-secret".into()),
+            ..AuthConfig::default()
+        },
+        security: Default::default(),
+    };
+    AppState::new(config, UserStore::empty())
+}
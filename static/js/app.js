@@ -12,6 +12,7 @@ class App {
   setupEventListeners() {
     document.addEventListener("DOMContentLoaded", () => {
       this.setupRefreshButton();
+      this.setupLogoutForm();
       this.initializeCards();
     });
   }
@@ -79,6 +80,19 @@ class App {
     }
   }
 
+  setupLogoutForm() {
+    const form = document.querySelector(".logout-form");
+    if (!form) return;
+
+    // The token rendered into the page may have expired by the time it is used
+    form.addEventListener("submit", async (event) => {
+      event.preventDefault();
+      await API.refreshCsrfToken();
+      form.elements.csrf_token.value = window.CSRF_TOKEN;
+      form.submit();
+    });
+  }
+
   async refreshData() {
     const button = document.getElementById("refreshButton");
     const lastUpdateEl = document.getElementById("lastUpdate");
@@ -88,7 +102,7 @@ class App {
     button.textContent = "Refreshing...";
 
     try {
-      const data = await API.refreshData();
+      const data = await API.refreshData(releasesContainer.dataset.feed);
 
       if (data.success) {
         const visibleReleases = data.releases.filter(
@@ -147,6 +161,20 @@ class App {
             "success",
           );
 
+          // Keep partially requested shows around with the remaining seasons
+          const seasonSelect = card.querySelector(".season-select");
+          if (seasonSelect && seasons !== "all") {
+            seasonSelect.querySelector(`option[value="${seasons}"]`)?.remove();
+            const remaining = Array.from(seasonSelect.options).filter(
+              (option) => option.value !== "all" && parseInt(option.value) > 0,
+            );
+            if (remaining.length > 0) {
+              button.disabled = false;
+              button.textContent = "Request Season";
+              return;
+            }
+          }
+
           card.style.transition = "all 0.3s ease";
           card.style.opacity = "0";
           card.style.transform = "scale(0.9)";
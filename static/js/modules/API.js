@@ -1,5 +1,5 @@
 export class API {
-  static async fetchFromAPI(endpoint, options = {}) {
+  static async fetchFromAPI(endpoint, options = {}, retried = false) {
     const defaultOptions = {
       headers: {
         "X-CSRF-Token": window.CSRF_TOKEN,
@@ -18,13 +18,47 @@ export class API {
 
     const response = await fetch(endpoint, finalOptions);
 
+    // Each accepted call hands out a fresh CSRF token for the next one
+    const nextToken = response.headers.get("X-CSRF-Token");
+    if (nextToken) {
+      window.CSRF_TOKEN = nextToken;
+    }
+
+    // The session expired or was signed out elsewhere
+    if (response.status === 401) {
+      window.location.href = "/login";
+      throw new Error("Please sign in again");
+    }
+
     if (!response.ok) {
-      throw new Error(`API call failed: ${response.status}`);
+      const body = await response.json().catch(() => null);
+
+      // The page outlived its CSRF token: get a fresh one and try once more
+      if (
+        response.status === 403 &&
+        body?.error?.startsWith("CSRF") &&
+        !retried
+      ) {
+        await this.refreshCsrfToken();
+        return this.fetchFromAPI(endpoint, options, true);
+      }
+
+      throw new Error(body?.error || `API call failed: ${response.status}`);
     }
 
     return response.json();
   }
 
+  static async refreshCsrfToken() {
+    const response = await fetch("/api/csrf-token");
+    if (!response.ok) {
+      // Nothing can be sent without a token, so start over with a fresh page
+      window.location.reload();
+      throw new Error("Please reload the page");
+    }
+    window.CSRF_TOKEN = (await response.json()).token;
+  }
+
   static async requestMedia(mediaType, id, seasons = null) {
     const requestData = {
       mediaType,
@@ -32,7 +66,7 @@ export class API {
     };
 
     if (mediaType === "tv") {
-      requestData.seasons = seasons === "all" ? [] : [parseInt(seasons)];
+      requestData.seasons = seasons === "all" ? "all" : [parseInt(seasons)];
     }
 
     return this.fetchFromAPI(`/api/request/${mediaType}/${id}`, {
@@ -41,8 +75,12 @@ export class API {
     });
   }
 
-  static async refreshData() {
-    return this.fetchFromAPI("/api/refresh", {
+  static async refreshData(feed) {
+    const endpoint = feed
+      ? `/api/refresh/${encodeURIComponent(feed)}`
+      : "/api/refresh";
+
+    return this.fetchFromAPI(endpoint, {
       method: "POST",
     });
   }
@@ -19,6 +19,29 @@ export class ReleaseManager {
     );
   }
 
+  // Release fields come from TMDB, OMDB and Jellyseerr and are interpolated
+  // into markup, so they must not be able to add elements or attributes
+  static escapeHtml(value) {
+    return String(value ?? "")
+      .replace(/&/g, "&amp;")
+      .replace(/</g, "&lt;")
+      .replace(/>/g, "&gt;")
+      .replace(/"/g, "&quot;")
+      .replace(/'/g, "&#39;");
+  }
+
+  static statusLabel(status) {
+    return this.escapeHtml(this.STATUS_LABELS[status] || status);
+  }
+
+  static STATUS_LABELS = {
+    pending: "Pending",
+    processing: "Processing",
+    partially_available: "Partially available",
+    available: "Available",
+    blacklisted: "Blacklisted",
+  };
+
   static createReleaseCard(release) {
     const formattedRating = Number(release.vote_average).toFixed(1);
 
@@ -33,11 +56,20 @@ export class ReleaseManager {
     }
 
     // Create season options if it's a TV show
+    const seasons = release.seasons || [];
     let seasonOptions = "";
-    if (release.media_type === "tv" && release.number_of_seasons) {
-      for (let i = 1; i <= release.number_of_seasons; i++) {
-        seasonOptions += `<option value="${i}">Season ${i}</option>`;
-      }
+    if (release.media_type === "tv" && seasons.length) {
+      seasonOptions = seasons
+        .map((season) => {
+          const year = season.air_date
+            ? ` (${this.escapeHtml(season.air_date.slice(0, 4))})`
+            : "";
+          const status = season.media_status
+            ? ` · ${this.statusLabel(season.media_status)}`
+            : "";
+          return `<option value="${Number(season.season_number)}">${this.escapeHtml(season.name)}${year} · ${Number(season.episode_count)} ep.${status}</option>`;
+        })
+        .join("");
       seasonOptions += '<option value="all">All Seasons</option>';
     }
 
@@ -47,7 +79,7 @@ export class ReleaseManager {
                   release.imdb_rating
                     ? `<div class="rating imdb">
                         <span class="rating-label">IMDb:</span>
-                        <span class="rating-value">${release.imdb_rating}</span>
+                        <span class="rating-value">${this.escapeHtml(release.imdb_rating)}</span>
                     </div>`
                     : ""
                 }
@@ -55,7 +87,7 @@ export class ReleaseManager {
                   release.metascore
                     ? `<div class="rating metascore">
                         <span class="rating-label">Metascore:</span>
-                        <span class="rating-value">${release.metascore}</span>
+                        <span class="rating-value">${this.escapeHtml(release.metascore)}</span>
                     </div>`
                     : ""
                 }
@@ -63,7 +95,7 @@ export class ReleaseManager {
                   release.rotten_tomatoes
                     ? `<div class="rating rt">
                         <span class="rating-label">RT:</span>
-                        <span class="rating-value">${release.rotten_tomatoes}</span>
+                        <span class="rating-value">${this.escapeHtml(release.rotten_tomatoes)}</span>
                     </div>`
                     : ""
                 }
@@ -73,39 +105,62 @@ export class ReleaseManager {
       release.imdb_rating || release.metascore || release.rotten_tomatoes;
     const ratingsSection = hasRatings ? ratingsHtml : "";
 
+    const providersSection = this.createProvidersSection(release.providers);
+
     return `
-            <div class="release-card ${ratingClass}" data-id="${release.id}">
+            <div class="release-card ${ratingClass}" data-id="${Number(release.id)}">
                 <div class="image-wrapper">
                     <div class="rating-overlay">
                         ${formattedRating}
                     </div>
-                    <img src="${release.poster_url}"
-                         alt="${release.title} poster"
+                    ${
+                      release.media_status
+                        ? `<div class="status-badge status-${this.escapeHtml(release.media_status)}">
+                        ${this.statusLabel(release.media_status)}
+                    </div>`
+                        : ""
+                    }
+                    <img src="${this.escapeHtml(release.poster_url)}"
+                         alt="${this.escapeHtml(release.title)} poster"
                          loading="lazy">
                     <div class="tmdb-overlay">
-                        <a href="${release.tmdb_url}"
+                        <a href="${this.escapeHtml(release.tmdb_url)}"
                            class="tmdb-link"
                            target="_blank">
                             View on TMDB
                         </a>
+                        ${
+                          release.imdb_url
+                            ? `<a href="${this.escapeHtml(release.imdb_url)}"
+                           class="tmdb-link imdb-link"
+                           target="_blank">
+                            View on IMDb
+                        </a>`
+                            : ""
+                        }
                     </div>
                 </div>
                 <div class="release-info">
                     <div class="content">
-                        <h3>${release.title}</h3>
-                        <p>Release Date: ${release.release_date}</p>
+                        <h3>${this.escapeHtml(release.title)}</h3>
+                        <p>Release Date: ${this.escapeHtml(release.release_date)}</p>
+                        ${
+                          release.genres && release.genres.length
+                            ? `<p class="genres">${release.genres.map((genre) => this.escapeHtml(genre)).join(" · ")}</p>`
+                            : ""
+                        }
                         <div class="description-container">
-                                            <p class="description-text">${release.overview || "No description available."}</p>
+                                            <p class="description-text">${this.escapeHtml(release.overview || "No description available.")}</p>
                                             <button class="description-toggle">Show More</button>
                                         </div>
                         ${ratingsSection}
+                        ${providersSection}
                         ${
-                          release.media_type === "tv" &&
-                          release.number_of_seasons
+                          release.media_type === "tv" && seasons.length
                             ? `
                             <div class="season-selector">
-                                <label for="season-${release.id}">Season:</label>
-                                <select id="season-${release.id}" class="season-select">
+                                <label for="season-${Number(release.id)}">Season:</label>
+                                <select id="season-${Number(release.id)}" class="season-select">
                                     ${seasonOptions}
                                 </select>
                             </div>
@@ -115,13 +170,13 @@ export class ReleaseManager {
                     </div>
                     <div class="button-group">
                         <button class="request-button"
-                                data-media-type="${release.media_type}"
-                                data-id="${release.id}">
+                                data-media-type="${this.escapeHtml(release.media_type)}"
+                                data-id="${Number(release.id)}">
                             Request ${release.media_type === "tv" ? "Season" : "Movie"}
                         </button>
                         <button class="hide-button"
-                                data-media-type="${release.media_type}"
-                                data-id="${release.id}">
+                                data-media-type="${this.escapeHtml(release.media_type)}"
+                                data-id="${Number(release.id)}">
                             Hide
                         </button>
                     </div>
@@ -130,6 +185,41 @@ export class ReleaseManager {
         `;
   }
 
+  static createProvidersSection(providers = []) {
+    if (!providers || providers.length === 0) {
+      return "";
+    }
+
+    const labels = {
+      flatrate: "Stream",
+      free: "Free",
+      ads: "With ads",
+      rent: "Rent",
+      buy: "Buy",
+    };
+
+    const items = providers
+      .map((provider) => {
+        const name = this.escapeHtml(provider.name);
+        const type = this.escapeHtml(provider.monetization_type);
+        const label = this.escapeHtml(
+          labels[provider.monetization_type] || provider.monetization_type,
+        );
+        return `
+                <span class="provider provider-${type}"
+                      title="${name} (${label})">
+                    ${
+                      provider.logo_url
+                        ? `<img src="${this.escapeHtml(provider.logo_url)}" alt="${name}" loading="lazy">`
+                        : `<span class="provider-name">${name}</span>`
+                    }
+                </span>`;
+      })
+      .join("");
+
+    return `<div class="providers-container">${items}</div>`;
+  }
+
   static setupDescriptionToggles() {
     document.querySelectorAll(".description-container").forEach((container) => {
       const text = container.querySelector(".description-text");